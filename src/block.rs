@@ -6,12 +6,18 @@ use crate::withdraws::{Withdrawal, hash_withdrawals};
 use anyhow::Result;
 use crate::world_state::WorldStateTrie;
 use crate::common::constants::hashes;
+use crate::spec::SpecId;
 use rlp::RlpStream;
 use sha3::{Digest, Keccak256};
 
 const TARGET_BLOB_GAS_PER_BLOCK:u64 = 393216;
 const MIN_BASE_FEE_PER_BLOB_GAS:u64 = 1;
 const BLOB_BASE_FEE_UPDATE_FRACTION:u64 = 3338477;
+// EIP-7691 raises the blob target from 3 to 6 blobs in Prague
+const TARGET_BLOB_GAS_PER_BLOCK_PRAGUE:u64 = 786432;
+const BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE:u64 = 5007716;
+// EIP-1559: the base fee of the first London block
+const INITIAL_BASE_FEE:u64 = 1_000_000_000;
 
 #[derive(Debug)]
 pub struct BlockHeader {
@@ -89,26 +95,36 @@ fn fake_exponential(factor: U256, numerator: U256, denominator: U256) -> U256 {
         numerator_accum = (numerator_accum * numerator) / (denominator * i);
         i += U256::one();
     }
-    output // denominator
+    output / denominator
 }
 
-// header.excess_blob_gas = calc_excess_blob_gas(parent)
-pub fn calc_excess_blob_gas(parent: &BlockHeader) -> U256 {
+// header.excess_blob_gas = calc_excess_blob_gas(parent, spec)
+pub fn calc_excess_blob_gas(parent: &BlockHeader, spec: SpecId) -> U256 {
     let excess = parent.excess_blob_gas.unwrap_or(U256::zero());
     let used = parent.blob_gas_used.unwrap_or(U256::zero());
-    if excess + used < U256::from(TARGET_BLOB_GAS_PER_BLOCK) {
+    let target = if spec.is_enabled_in(SpecId::Prague) {
+        U256::from(TARGET_BLOB_GAS_PER_BLOCK_PRAGUE)
+    } else {
+        U256::from(TARGET_BLOB_GAS_PER_BLOCK)
+    };
+    if excess + used < target {
         U256::zero()
     } else {
-        excess + used - U256::from(TARGET_BLOB_GAS_PER_BLOCK)
+        excess + used - target
     }
 }
 
 impl Block {
-    pub fn get_base_fee_per_blob_gas(&self) -> U256 {
+    pub fn get_base_fee_per_blob_gas(&self, spec: SpecId) -> U256 {
+        let update_fraction = if spec.is_enabled_in(SpecId::Prague) {
+            BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE
+        } else {
+            BLOB_BASE_FEE_UPDATE_FRACTION
+        };
         fake_exponential(
             U256::from(MIN_BASE_FEE_PER_BLOB_GAS),
             self.header.excess_blob_gas.unwrap_or(U256::zero()),
-            U256::from(BLOB_BASE_FEE_UPDATE_FRACTION)
+            U256::from(update_fraction)
         )
     }
     pub fn holistic_validity_check(&self, state:&WorldStateTrie) -> Result<()> {
@@ -171,7 +187,7 @@ impl BlockHeader {
     }
 
     /// 验证区块头的有效性
-    pub fn header_validity_check(&self, parent: Option<&Block>) -> Result<()> {
+    pub fn header_validity_check(&self, parent: Option<&Block>, spec: SpecId) -> Result<()> {
         // 1. 静态规则检查（不依赖父区块）
         
         // H_g <= H_l: 已使用的 Gas 必须不超过 gas limit
@@ -194,15 +210,29 @@ impl BlockHeader {
         }
 
         // H_d = 0: 巴黎升级后 Difficulty 恒为 0
-        if self.difficulty != U256::zero() {
+        if spec.is_enabled_in(SpecId::Paris) && self.difficulty != U256::zero() {
             return Err(anyhow::anyhow!("Difficulty must be 0 (post-Paris)"));
         }
 
         // H_n = 0x0000000000000000: 巴黎升级后 Nonce 恒为 0
-        if self.nonce != H64::zero() {
+        if spec.is_enabled_in(SpecId::Paris) && self.nonce != H64::zero() {
             return Err(anyhow::anyhow!("Nonce must be zero (post-Paris)"));
         }
 
+        // 各硬分叉新增的区块头字段：有且仅有在激活之后出现
+        let fork_fields = [
+            ("base fee", self.base_fee.is_some(), SpecId::London),
+            ("withdrawals root", self.withdrawals_root.is_some(), SpecId::Shanghai),
+            ("blob gas used", self.blob_gas_used.is_some(), SpecId::Cancun),
+            ("excess blob gas", self.excess_blob_gas.is_some(), SpecId::Cancun),
+            ("parent beacon block root", self.parent_beacon_block_root.is_some(), SpecId::Cancun),
+        ];
+        for (field, present, fork) in fork_fields {
+            if present != spec.is_enabled_in(fork) {
+                return Err(anyhow::anyhow!("Header field {} must {}be present in {}", field, if present { "not " } else { "" }, spec));
+            }
+        }
+
         // H_a = PREVRANDAO(): 理论上需要信标链状态验证，此处仅做结构占位
         // (在实际客户端中，这里会调用共识层的接口来验证 prev_randao)
 
@@ -239,6 +269,12 @@ impl BlockHeader {
             }
 
             // H_f = F(H): 基础 Gas 费 (Base Fee) 计算与验证
+            // 伦敦升级的第一个区块，父区块没有 base_fee，使用初始值
+            if let (Some(base_fee), None) = (self.base_fee, p_header.base_fee) {
+                if base_fee != U256::from(INITIAL_BASE_FEE) {
+                    return Err(anyhow::anyhow!("Invalid base fee: expected {}, got {}", INITIAL_BASE_FEE, base_fee));
+                }
+            }
             if let (Some(base_fee), Some(p_base_fee)) = (self.base_fee, p_header.base_fee) {
                 let p_gas_used = p_header.gas_used;
                 let target = p_header.gas_limit / 2; // tau = P(H)_{H_l} / 2
@@ -265,8 +301,6 @@ impl BlockHeader {
                     return Err(anyhow::anyhow!("Invalid base fee: expected {}, got {}", expected_base_fee, base_fee));
                 }
             } 
-            // 注意：如果是刚好发生伦敦升级的那个区块，父区块没有 base_fee 但当前区块有，
-            // 这里的逻辑需要根据具体的硬分叉高度配置来放行。这里仅做基础的同构检查。
         } else { // genesis block
             if self.number != 0 {
                 return Err(anyhow::anyhow!("Non-genesis block must have a parent"));
@@ -463,3 +497,86 @@ impl Decodable for Block {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a block carrying exactly the header fields of `spec`
+    fn block_for(spec: SpecId, number: u64) -> Block {
+        let mut block = Block::default();
+        let header = &mut block.header;
+        header.number = number;
+        header.timestamp = number * 12;
+        header.gas_limit = U256::from(30_000_000);
+        header.base_fee = spec.is_enabled_in(SpecId::London).then(|| U256::from(INITIAL_BASE_FEE));
+        header.withdrawals_root = spec.is_enabled_in(SpecId::Shanghai).then_some(hashes::EMPTY_TRIE_HASH);
+        header.blob_gas_used = spec.is_enabled_in(SpecId::Cancun).then(U256::zero);
+        header.excess_blob_gas = spec.is_enabled_in(SpecId::Cancun).then(U256::zero);
+        header.parent_beacon_block_root = spec.is_enabled_in(SpecId::Cancun).then(H256::zero);
+        block
+    }
+
+    #[test]
+    fn test_header_fields_must_match_the_fork() {
+        for spec in [SpecId::Berlin, SpecId::London, SpecId::Paris, SpecId::Shanghai, SpecId::Cancun, SpecId::Prague] {
+            assert!(block_for(spec, 0).header.header_validity_check(None, spec).is_ok(), "{}", spec);
+        }
+        // a header of the next fork has an extra field
+        for (spec, next) in [(SpecId::Berlin, SpecId::London), (SpecId::Paris, SpecId::Shanghai), (SpecId::Shanghai, SpecId::Cancun)] {
+            assert!(block_for(next, 0).header.header_validity_check(None, spec).is_err(), "{} header in {}", next, spec);
+        }
+        // and each field introduced by a fork is required from then on
+        let missing: [(SpecId, fn(&mut BlockHeader)); 5] = [
+            (SpecId::London, |header| header.base_fee = None),
+            (SpecId::Shanghai, |header| header.withdrawals_root = None),
+            (SpecId::Cancun, |header| header.blob_gas_used = None),
+            (SpecId::Cancun, |header| header.excess_blob_gas = None),
+            (SpecId::Cancun, |header| header.parent_beacon_block_root = None),
+        ];
+        for (i, (spec, remove_field)) in missing.into_iter().enumerate() {
+            let mut block = block_for(spec, 0);
+            remove_field(&mut block.header);
+            assert!(block.header.header_validity_check(None, spec).is_err(), "field {} in {}", i, spec);
+        }
+    }
+
+    #[test]
+    fn test_difficulty_and_nonce_are_zero_from_paris() {
+        let mut block = block_for(SpecId::London, 0);
+        block.header.difficulty = U256::from(0x020000);
+        assert!(block.header.header_validity_check(None, SpecId::London).is_ok());
+        assert!(block.header.header_validity_check(None, SpecId::Paris).is_err());
+
+        let mut block = block_for(SpecId::London, 0);
+        block.header.nonce = H64::from_low_u64_be(0x42);
+        assert!(block.header.header_validity_check(None, SpecId::London).is_ok());
+        assert!(block.header.header_validity_check(None, SpecId::Paris).is_err());
+    }
+
+    #[test]
+    fn test_london_transition_block_has_the_initial_base_fee() {
+        let parent = block_for(SpecId::Berlin, 1);
+        let mut block = block_for(SpecId::London, 2);
+        block.header.parent_hash = parent.header.hash();
+        assert!(block.header.header_validity_check(Some(&parent), SpecId::London).is_ok());
+
+        block.header.base_fee = Some(U256::from(INITIAL_BASE_FEE - 1));
+        assert!(block.header.header_validity_check(Some(&parent), SpecId::London).is_err());
+    }
+
+    #[test]
+    fn test_blob_gas_follows_the_eip_7691_target_in_prague() {
+        let mut parent = block_for(SpecId::Cancun, 0);
+        parent.header.blob_gas_used = Some(U256::from(TARGET_BLOB_GAS_PER_BLOCK_PRAGUE));
+        let parent = &parent.header;
+        assert_eq!(calc_excess_blob_gas(parent, SpecId::Cancun), U256::from(TARGET_BLOB_GAS_PER_BLOCK));
+        assert_eq!(calc_excess_blob_gas(parent, SpecId::Prague), U256::zero());
+
+        // e^(excess / update fraction): e^1.5 in Cancun, e^1 in Prague
+        let mut block = block_for(SpecId::Cancun, 0);
+        block.header.excess_blob_gas = Some(U256::from(BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE));
+        assert_eq!(block.get_base_fee_per_blob_gas(SpecId::Cancun), U256::from(4));
+        assert_eq!(block.get_base_fee_per_blob_gas(SpecId::Prague), U256::from(2));
+    }
+}
@@ -1,13 +1,60 @@
 use crate::block::Block;
 use crate::world_state::WorldStateTrie;
-use crate::tx_execution::tx_execute;
+use crate::tx_execution::tx_execute_with_precompiles;
+use crate::operations::Precompiles;
+use crate::spec::{ForkSchedule, SpecId};
 use crate::withdraws::Withdrawal;
+use crate::common::crypto::load_kzg_trusted_setup;
 use ethereum_types::{Address, H256, U256};
 use anyhow::Result;
+use std::path::PathBuf;
+
+/// 构造 blockchain 所需的配置：分叉计划，以及 Cancun 起 point evaluation 预编译所需的 KZG trusted setup
+#[derive(Debug, Clone)]
+pub struct BlockchainConfig {
+    pub forks: ForkSchedule,
+    pub kzg_trusted_setup: Option<PathBuf>, // required once the schedule reaches Cancun
+}
+
+impl BlockchainConfig {
+    pub fn new(forks: impl Into<ForkSchedule>) -> Self {
+        Self { forks: forks.into(), kzg_trusted_setup: None }
+    }
+
+    pub fn with_kzg_trusted_setup(mut self, path: impl Into<PathBuf>) -> Self {
+        self.kzg_trusted_setup = Some(path.into());
+        self
+    }
+
+    // 加载 KZG trusted setup；分叉计划到达 Cancun 却没有给出路径时报错
+    fn load_kzg_trusted_setup(&self) -> Result<()> {
+        match &self.kzg_trusted_setup {
+            Some(path) => load_kzg_trusted_setup(path),
+            None if self.forks.reaches(SpecId::Cancun) => {
+                Err(anyhow::anyhow!("the fork schedule reaches Cancun, but no KZG trusted setup path is configured"))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl From<SpecId> for BlockchainConfig {
+    fn from(spec: SpecId) -> Self {
+        Self::new(spec)
+    }
+}
+
+impl From<ForkSchedule> for BlockchainConfig {
+    fn from(forks: ForkSchedule) -> Self {
+        Self::new(forks)
+    }
+}
 
 pub struct Blockchain {
     pub blocks: Vec<Block>,
     pub state: WorldStateTrie,
+    pub forks: ForkSchedule, // picks the fork whose rules each new block is validated and executed with
+    pub precompiles: Precompiles, // added on top of the fork's precompiles, replacing any at the same address
 }
 
 impl Blockchain {
@@ -21,48 +68,52 @@ impl Blockchain {
         ])
     }
 
-    /// 创建一个新的空 blockchain（从 genesis 开始）
-    pub fn new() -> Self {
-        Self {
-            blocks: vec![],
-            state: WorldStateTrie::new(),
-        }
+    /// 创建一个新的空 blockchain（从 genesis 开始），config 可以是单个 SpecId、分叉计划或完整的 BlockchainConfig；
+    /// 分叉计划到达 Cancun 时在此加载 KZG trusted setup，未配置路径或加载失败时返回错误
+    pub fn new(config: impl Into<BlockchainConfig>) -> Result<Self> {
+        Self::with_blocks_and_state(vec![], WorldStateTrie::new(), config)
     }
 
     /// 从指定的初始 state 创建 blockchain
-    pub fn with_state(state: WorldStateTrie) -> Self {
-        Self {
-            blocks: vec![],
-            state,
-        }
+    pub fn with_state(state: WorldStateTrie, config: impl Into<BlockchainConfig>) -> Result<Self> {
+        Self::with_blocks_and_state(vec![], state, config)
     }
 
     /// 从已有的 blocks 和 state 创建 blockchain
-    pub fn with_blocks_and_state(blocks: Vec<Block>, state: WorldStateTrie) -> Self {
-        Self {
+    pub fn with_blocks_and_state(blocks: Vec<Block>, state: WorldStateTrie, config: impl Into<BlockchainConfig>) -> Result<Self> {
+        let config = config.into();
+        config.load_kzg_trusted_setup()?;
+        Ok(Self {
             blocks,
             state,
-        }
+            forks: config.forks,
+            precompiles: Precompiles::default(),
+        })
     }
 
     pub fn add_block(&mut self, mut block: Block) -> Result<()> {
         // 1. header_validity_check
+        let spec = self.forks.spec_at(block.header.number, block.header.timestamp)?;
         let parent = self.blocks.last();
-        block.header.header_validity_check(parent)?;
+        block.header.header_validity_check(parent, spec)?;
 
         // 2. 加载旧 WorldStateTrie (使用当前的 self.state)
         // 注意：这里我们直接使用 self.state，因为它是当前的世界状态
 
         // 3. 执行区块级系统写入（EIP-4788 beacon roots contract）
-        self.process_beacon_root_contract(&block)?;
+        if spec.is_enabled_in(SpecId::Cancun) {
+            self.process_beacon_root_contract(&block)?;
+        }
 
         // 4. 执行所有交易
         // 先克隆 transactions 以避免借用冲突
         let transactions = block.transactions.clone();
         let mut cumulative_gas_used = U256::zero();
+        let mut precompiles = Precompiles::for_spec(spec).clone();
+        precompiles.extend(&self.precompiles);
         
         for tx in &transactions {
-            tx_execute(tx, &mut self.state, &mut block)?;
+            tx_execute_with_precompiles(tx, &mut self.state, &mut block, &self.blocks, spec, &precompiles)?;
             
             // 更新累计 gas_used
             // tx_execute 中 receipt 的 cumulative_gas_used 只是该交易的 gas_used
@@ -76,7 +127,9 @@ impl Blockchain {
         block.header.gas_used = cumulative_gas_used;
 
         // 5. 处理withdraw (留好接口，todo)
-        self.process_withdrawals(&block.withdrawals)?;
+        if spec.is_enabled_in(SpecId::Shanghai) {
+            self.process_withdrawals(&block.withdrawals)?;
+        }
 
         // 6. holistic_validity_check
         // 对于当前简化执行器，state_root 可能暂时与 fixture 不一致；
@@ -149,3 +202,88 @@ impl Blockchain {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::Precompile;
+    use crate::spec::ForkActivation;
+    use crate::transaction::Transaction1or2;
+    use crate::tx_execution::{Context, EvmError, Machine};
+    use crate::world_state::AccountState;
+    use bytes::Bytes;
+    use hex_literal::hex;
+
+    // a legacy transaction from 0xa94f...0b calling 0x02 with "abc", gas price 10
+    const SHA256_CALL_TX: [u8; 102] = hex!(
+        "f864800a8404c4b40094000000000000000000000000000000000000000280836162631ca0884134f22c6ba7ce6505f29bf66d32d4ca38ed252b1041269bd600ceea7d2ee1a032d54d7bb9f2bb325a7959817451b400fbff70ce08073365ac29232069e015ce"
+    );
+
+    struct Flat;
+    impl Precompile for Flat {
+        fn execute(&self, _evm: &mut Machine, _context: &Context) -> Result<Option<Bytes>, EvmError> {
+            Ok(None)
+        }
+
+        fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+            U256::from(5000)
+        }
+    }
+
+    // a pre-London header, without base fee, withdrawals and blob fields
+    fn berlin_block(number: u64) -> Block {
+        let mut block = Block::default();
+        block.header.number = number;
+        block.header.timestamp = number * 12;
+        block.header.gas_limit = U256::from(30_000_000);
+        block.header.base_fee = None;
+        block.header.withdrawals_root = None;
+        block.header.blob_gas_used = None;
+        block.header.excess_blob_gas = None;
+        block
+    }
+
+    #[test]
+    fn test_registered_precompile_replaces_the_fork_one() {
+        let tx = Transaction1or2::deserialization(&SHA256_CALL_TX).unwrap();
+        let mut state = WorldStateTrie::new();
+        let mut sender = AccountState::default();
+        sender.balance = U256::from(10).pow(U256::from(18));
+        state.insert(&tx.get_sender().unwrap(), sender);
+
+        let mut chain = Blockchain::with_state(state, SpecId::Berlin).unwrap();
+        chain.precompiles.register(Address::from_low_u64_be(0x02), Flat);
+        let mut block = berlin_block(0);
+        block.header.gas_limit = U256::from(100_000_000);
+        block.add_transaction(tx);
+        chain.add_block(block).unwrap();
+
+        // intrinsic gas for "abc" and the registered precompile's 5000 instead of SHA-256's 72
+        let receipt = &chain.get_latest_block().unwrap().receipts[0];
+        assert_eq!(receipt.cumulative_gas_used, U256::from(21000 + 3 * 16 + 5000));
+    }
+
+    #[test]
+    fn test_blocks_follow_the_fork_schedule() {
+        let forks = ForkSchedule::fixed(SpecId::Berlin).with_fork(SpecId::London, ForkActivation::Block(1));
+        let mut chain = Blockchain::new(forks).unwrap();
+        chain.add_block(berlin_block(0)).unwrap();
+
+        // the transition block is validated with the London rules against its Berlin parent
+        let mut block = berlin_block(1);
+        block.header.parent_hash = chain.get_latest_block().unwrap().header.hash();
+        block.header.base_fee = Some(U256::from(1_000_000_000));
+        chain.add_block(block).unwrap();
+
+        assert!(Blockchain::new(SpecId::London).unwrap().add_block(berlin_block(0)).is_err());
+    }
+
+    #[test]
+    fn test_cancun_chains_need_the_kzg_trusted_setup() {
+        // the setup itself is not loaded here, the point evaluation tests rely on it being absent
+        assert!(Blockchain::new(SpecId::Cancun).is_err());
+        assert!(Blockchain::new(ForkSchedule::mainnet()).is_err());
+        let missing = BlockchainConfig::new(SpecId::Cancun).with_kzg_trusted_setup("no_such_trusted_setup.txt");
+        assert!(Blockchain::new(missing).is_err());
+        assert!(Blockchain::new(SpecId::Shanghai).is_ok());
+    }
+}
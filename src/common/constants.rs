@@ -8,3 +8,16 @@ pub mod hashes {
     pub const EMPTY_TRIE_HASH: H256 = H256(hex!("56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"));
 }
 
+
+pub mod chain {
+    // mainnet, returned by the CHAINID opcode
+    pub const CHAIN_ID: u64 = 1;
+}
+
+pub mod kzg {
+    use hex_literal::hex;
+    // EIP-4844 point evaluation precompile
+    pub const FIELD_ELEMENTS_PER_BLOB: u64 = 4096;
+    pub const BLS_MODULUS: [u8; 32] = hex!("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001");
+    pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+}
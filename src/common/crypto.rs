@@ -2,6 +2,11 @@ use k256::ecdsa::{Signature, RecoveryId, VerifyingKey,SigningKey};
 use sha3::{Digest, Keccak256};
 use ethereum_types::{Address, H256, U256};
 use anyhow::Result;
+use c_kzg::{Bytes32, Bytes48, KzgProof, KzgSettings};
+use once_cell::sync::OnceCell;
+use sha2::Sha256;
+use std::path::Path;
+use crate::common::constants::kzg;
 
 pub fn public_key_to_eth_address(pubkey: &VerifyingKey) -> Address {
     let pubkey_encoded = pubkey.to_encoded_point(false); // uncompressed, starts with 0x04
@@ -57,6 +62,41 @@ pub fn sign_message_hash(message_hash: H256, signing_key: &SigningKey) -> (U256,
     (r, s, v)
 }
 
+static KZG_SETTINGS: OnceCell<KzgSettings> = OnceCell::new();
+
+/// Load the KZG trusted setup used by the point evaluation precompile. `Blockchain` loads it from
+/// its config, call it directly before executing Cancun or later transactions without one;
+/// once a setup is loaded later calls keep it.
+pub fn load_kzg_trusted_setup(path: &Path) -> Result<()> {
+    KZG_SETTINGS.get_or_try_init(|| {
+        KzgSettings::load_trusted_setup_file(path)
+            .map_err(|e| anyhow::anyhow!("cannot load the KZG trusted setup from {}: {:?}", path.display(), e))
+    })?;
+    Ok(())
+}
+
+/// EIP-4844: the versioned hash of a blob commitment is its sha256 with the first byte set to the version.
+pub fn kzg_to_versioned_hash(commitment: &[u8]) -> H256 {
+    let mut hash: [u8; 32] = Sha256::digest(commitment).into();
+    hash[0] = kzg::VERSIONED_HASH_VERSION_KZG;
+    H256(hash)
+}
+
+/// Check that `proof` opens `commitment` to `y` at `z`. A trusted setup that was never loaded is
+/// an error, malformed inputs and a proof that does not verify are `Ok(false)`.
+pub fn verify_kzg_proof(commitment: &[u8], z: &[u8], y: &[u8], proof: &[u8]) -> Result<bool> {
+    let settings = KZG_SETTINGS.get()
+        .ok_or_else(|| anyhow::anyhow!("the KZG trusted setup is not loaded"))?;
+    let verified = (|| KzgProof::verify_kzg_proof(
+        &Bytes48::from_bytes(commitment)?,
+        &Bytes32::from_bytes(z)?,
+        &Bytes32::from_bytes(y)?,
+        &Bytes48::from_bytes(proof)?,
+        settings,
+    ))();
+    Ok(verified.unwrap_or(false))
+}
+
 
 #[cfg(test)]
 mod tests {
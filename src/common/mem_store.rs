@@ -4,6 +4,7 @@
 use ethereum_types::{H256, U256};
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 thread_local! {
     /// 解码 AccountState 时若设置，则用其填充 code 与 storage
@@ -73,16 +74,45 @@ impl MemStorageStore {
     }
 }
 
+/// JUMPDEST 位图：第 i 位为 1 表示 code[i] 是合法跳转目标（JUMPDEST 且不在 PUSH 数据中）
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct JumpdestBitmap {
+    bits: Vec<u64>,
+}
+
+impl JumpdestBitmap {
+    /// 创建可容纳 len 字节 code 的全 0 位图
+    pub fn with_len(len: usize) -> Self {
+        Self {
+            bits: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    pub fn set(&mut self, index: usize) {
+        self.bits[index / 64] |= 1 << (index % 64);
+    }
+
+    /// 越界的位置视为非法跳转目标
+    pub fn contains(&self, index: usize) -> bool {
+        self.bits
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+}
+
 /// 模拟持久化 code 存储：根据 code_hash 得到合约字节码
 #[derive(Default, Clone)]
 pub struct MemCodeStore {
     by_hash: HashMap<H256, Vec<u8>>,
+    /// 按 code_hash 缓存的 JUMPDEST 分析结果，同一合约只扫描一次
+    jumpdests: HashMap<H256, Arc<JumpdestBitmap>>,
 }
 
 impl MemCodeStore {
     pub fn new() -> Self {
         Self {
             by_hash: HashMap::new(),
+            jumpdests: HashMap::new(),
         }
     }
 
@@ -95,6 +125,16 @@ impl MemCodeStore {
     pub fn put(&mut self, code_hash: H256, code: Vec<u8>) {
         self.by_hash.insert(code_hash, code);
     }
+
+    /// 根据 code_hash 取出缓存的 JUMPDEST 位图
+    pub fn get_jumpdests(&self, code_hash: H256) -> Option<Arc<JumpdestBitmap>> {
+        self.jumpdests.get(&code_hash).cloned()
+    }
+
+    /// 以 code_hash 为键缓存 JUMPDEST 位图
+    pub fn put_jumpdests(&mut self, code_hash: H256, bitmap: Arc<JumpdestBitmap>) {
+        self.jumpdests.insert(code_hash, bitmap);
+    }
 }
 
 /// 合并的模拟后端：同时提供 storage 与 code 的按根/按 hash 存取
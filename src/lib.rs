@@ -3,6 +3,7 @@ pub mod block;
 pub mod blockchain;
 pub mod operations;
 pub mod receipts;
+pub mod spec;
 pub mod transaction;
 pub mod tx_execution;
 pub mod withdraws;
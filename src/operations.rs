@@ -1,9 +1,17 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 use once_cell::sync::Lazy;
 use crate::tx_execution::{Machine,Context, Substate, ExecuteResult, EvmError};
+use crate::tx_execution::{create_address, G_CODE_DEPOSIT, MAX_CODE_SIZE, MAX_INITCODE_SIZE};
 use crate::world_state::{WorldStateTrie, AccountState};
+use crate::common::crypto;
+use crate::common::mem_store::JumpdestBitmap;
+use crate::common::constants::chain::CHAIN_ID;
+use crate::common::constants::kzg;
+use crate::receipts::Log;
+use crate::spec::SpecId;
 use bytes::Bytes;
-use ethereum_types::{Address, U256};
+use ethereum_types::{Address, H256, U256, U512};
 use sha3::{Digest, Keccak256};
 
 type ExecutionFunc = fn(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult;
@@ -13,16 +21,95 @@ type MemorySizeFunc = fn(evm: &Machine, context: &Context) -> Result<usize, EvmE
 pub mod opcodes {
     pub const STOP: u8 = 0x00;
     pub const ADD: u8 = 0x01;
+    pub const MUL: u8 = 0x02;
+    pub const SUB: u8 = 0x03;
+    pub const DIV: u8 = 0x04;
+    pub const SDIV: u8 = 0x05;
+    pub const MOD: u8 = 0x06;
+    pub const SMOD: u8 = 0x07;
+    pub const ADDMOD: u8 = 0x08;
+    pub const MULMOD: u8 = 0x09;
+    pub const EXP: u8 = 0x0A;
+    pub const SIGNEXTEND: u8 = 0x0B;
+    pub const LT: u8 = 0x10;
+    pub const GT: u8 = 0x11;
+    pub const SLT: u8 = 0x12;
+    pub const SGT: u8 = 0x13;
+    pub const EQ: u8 = 0x14;
+    pub const ISZERO: u8 = 0x15;
+    pub const AND: u8 = 0x16;
+    pub const OR: u8 = 0x17;
+    pub const XOR: u8 = 0x18;
+    pub const NOT: u8 = 0x19;
+    pub const BYTE: u8 = 0x1A;
+    pub const SHL: u8 = 0x1B;
+    pub const SHR: u8 = 0x1C;
+    pub const SAR: u8 = 0x1D;
+    pub const KECCAK256: u8 = 0x20;
+    pub const ADDRESS: u8 = 0x30;
+    pub const BALANCE: u8 = 0x31;
+    pub const ORIGIN: u8 = 0x32;
+    pub const CALLER: u8 = 0x33;
+    pub const CALLVALUE: u8 = 0x34;
     pub const CALLDATALOAD: u8 = 0x35;
+    pub const CALLDATASIZE: u8 = 0x36;
+    pub const CALLDATACOPY: u8 = 0x37;
+    pub const CODESIZE: u8 = 0x38;
+    pub const CODECOPY: u8 = 0x39;
+    pub const GASPRICE: u8 = 0x3A;
+    pub const EXTCODESIZE: u8 = 0x3B;
+    pub const EXTCODECOPY: u8 = 0x3C;
+    pub const RETURNDATASIZE: u8 = 0x3D;
+    pub const RETURNDATACOPY: u8 = 0x3E;
+    pub const EXTCODEHASH: u8 = 0x3F;
+    pub const BLOCKHASH: u8 = 0x40;
+    pub const COINBASE: u8 = 0x41;
+    pub const TIMESTAMP: u8 = 0x42;
+    pub const NUMBER: u8 = 0x43;
+    pub const PREVRANDAO: u8 = 0x44;
+    pub const GASLIMIT: u8 = 0x45;
+    pub const CHAINID: u8 = 0x46;
+    pub const SELFBALANCE: u8 = 0x47;
+    pub const BASEFEE: u8 = 0x48;
+    pub const BLOBHASH: u8 = 0x49;
+    pub const BLOBBASEFEE: u8 = 0x4A;
+    pub const POP: u8 = 0x50;
+    pub const MLOAD: u8 = 0x51;
+    pub const MSTORE: u8 = 0x52;
+    pub const MSTORE8: u8 = 0x53;
+    pub const SLOAD: u8 = 0x54;
     pub const SSTORE: u8 = 0x55;
+    pub const JUMP: u8 = 0x56;
+    pub const JUMPI: u8 = 0x57;
+    pub const PC: u8 = 0x58;
+    pub const MSIZE: u8 = 0x59;
+    pub const GAS: u8 = 0x5A;
+    pub const JUMPDEST: u8 = 0x5B;
+    pub const TLOAD: u8 = 0x5C;
+    pub const TSTORE: u8 = 0x5D;
+    pub const MCOPY: u8 = 0x5E;
+    pub const PUSH0: u8 = 0x5F;
     pub const PUSH1: u8 = 0x60;
     pub const PUSH32: u8 = 0x7f;
+    pub const DUP1: u8 = 0x80;
+    pub const DUP16: u8 = 0x8F;
+    pub const SWAP1: u8 = 0x90;
+    pub const SWAP16: u8 = 0x9F;
+    pub const LOG0: u8 = 0xA0;
+    pub const LOG4: u8 = 0xA4;
     pub const CREATE: u8 = 0xF0;
     pub const CALL: u8 = 0xF1;
+    pub const CALLCODE: u8 = 0xF2;
+    pub const RETURN: u8 = 0xF3;
+    pub const DELEGATECALL: u8 = 0xF4;
+    pub const CREATE2: u8 = 0xF5;
+    pub const STATICCALL: u8 = 0xFA;
+    pub const REVERT: u8 = 0xFD;
     pub const SELFDESTRUCT: u8 = 0xFF;
 }
 
 
+#[derive(Clone)]
 pub struct Operation {
     pub opcode: u8,
     pub execute: ExecutionFunc,
@@ -58,6 +145,14 @@ impl Operation {
 
 pub type JumpTable = HashMap<u8, Operation>; // map opcode to operation
 
+const STACK_LIMIT: usize = 1024;
+
+// the largest stack size before an operation that pops `pop` items and pushes `push` items,
+// so that the stack never grows beyond STACK_LIMIT after execution
+const fn max_stack(pop: usize, push: usize) -> usize {
+    STACK_LIMIT + pop - push
+}
+
 
 fn op_stop(_evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
     // STOP: Halts execution successfully
@@ -74,588 +169,3197 @@ fn op_add(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTri
     Ok(Bytes::new())
 }
 
-fn op_calldataload(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
-    let offset = evm.stack_pop()?.as_usize();
-    let mut buf = [0u8; 32];
-    if offset < context.input.len() { // calldata not long enough, pad right with 0
-        let available = std::cmp::min(32, context.input.len() - offset);
-        buf[..available].copy_from_slice(&context.input[offset..offset + available]);
-    }
-    evm.stack_push(U256::from_big_endian(&buf))?;
+fn op_mul(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    let (result, _overflow) = a.overflowing_mul(b);
+    evm.stack_push(result)?;
     Ok(Bytes::new())
 }
 
-fn op_sstore(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    // 1. Gas Stipend 检查 (EIP-2200)
-    if evm.gas_remaining <= U256::from(2300u64) {
-        return Err(EvmError::OutOfGas);
-    }
+fn op_sub(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    let (result, _overflow) = a.overflowing_sub(b);
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
+}
 
-    let key = evm.stack_pop()?;
-    let new_value = evm.stack_pop()?;
-    let addr = context.contract_addr.unwrap_or(context.sender);
+fn op_div(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    // division by zero yields 0 in EVM
+    let result = if b.is_zero() { U256::zero() } else { a / b };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
+}
 
-    let current_value = worldstate.get_storage(&addr, key).unwrap_or(U256::zero());
-    let original_value = worldstate.get_original_storage(&addr, key).unwrap_or(U256::zero());
+/* ------------------- two's complement helpers for signed ops ------------------ */
+fn is_negative(value: U256) -> bool {
+    value.bit(255)
+}
 
-    // 2. EIP-2929 Cold storage access cost
-    let mut key_bytes = [0u8; 32];
-    let key_be = key.to_big_endian();
-    if key_be.len() >= 32 {
-        key_bytes.copy_from_slice(&key_be[key_be.len() - 32..]);
+fn twos_complement(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+fn abs_value(value: U256) -> U256 {
+    if is_negative(value) { twos_complement(value) } else { value }
+}
+
+fn op_sdiv(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    let result = if b.is_zero() {
+        U256::zero()
     } else {
-        key_bytes[32 - key_be.len()..].copy_from_slice(&key_be);
-    }
-    let key_h256 = ethereum_types::H256::from_slice(&key_bytes);
-    let slot = (addr, key_h256);
-    if !substate.access_list_storage.contains(&slot) {
-        let cold_cost = U256::from(2100u64);
-        if cold_cost > evm.gas_remaining {
-            return Err(EvmError::OutOfGas);
-        }
-        evm.gas_remaining -= cold_cost;
-        substate.access_list_storage.push(slot);
-    }
+        // -2^255 / -1 overflows back to -2^255, which falls out of the unsigned division naturally
+        let quotient = abs_value(a) / abs_value(b);
+        if is_negative(a) != is_negative(b) { twos_complement(quotient) } else { quotient }
+    };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
+}
 
-    // 3. EIP-2200 / EIP-2929 
-    let sstore_cost: u64;
+fn op_mod(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    let result = if b.is_zero() { U256::zero() } else { a % b };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
+}
 
-    if current_value == new_value {
-        sstore_cost = 100; // SLOAD_GAS (Warm)
+fn op_smod(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    let result = if b.is_zero() {
+        U256::zero()
     } else {
-        if original_value == current_value { // Clean slot
-            if original_value.is_zero() { 
-                sstore_cost = 20_000; // SSTORE_SET_GAS
-            } else {
-                sstore_cost = 2_900;  // SSTORE_RESET_GAS (5000 - 2100)
-                if new_value.is_zero() {
-                    substate.refund_fee += U256::from(15_000u64); // SSTORE_CLEARS_SCHEDULE
-                }
-            }
-        } else { // Dirty slot 
-            sstore_cost = 100; // SLOAD_GAS (Warm)
+        // the sign of the result follows the dividend
+        let remainder = abs_value(a) % abs_value(b);
+        if is_negative(a) { twos_complement(remainder) } else { remainder }
+    };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
+}
 
-            if !original_value.is_zero() {
-                if current_value.is_zero() {
-                    substate.refund_fee -= U256::from(15_000u64);
-                }
-                if new_value.is_zero() {
-                    substate.refund_fee += U256::from(15_000u64);
-                }
-            }
-            if original_value == new_value {
-                if original_value.is_zero() {
-                    substate.refund_fee += U256::from(20_000u64 - 100u64);
-                } else {
-                    substate.refund_fee += U256::from(2_900u64 - 100u64);
-                }
-            }
-        }
-    }
+fn op_addmod(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    let n = evm.stack_pop()?;
+    // the intermediate sum is not subject to the 2^256 modulo
+    let result = if n.is_zero() {
+        U256::zero()
+    } else {
+        let sum = U512::from(a) + U512::from(b);
+        U256::try_from(sum % U512::from(n)).expect("remainder is less than n")
+    };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
+}
 
-    let sstore_cost_u256 = U256::from(sstore_cost);
-    if sstore_cost_u256 > evm.gas_remaining {
-        return Err(EvmError::OutOfGas);
-    }
-    evm.gas_remaining -= sstore_cost_u256;
+fn op_mulmod(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    let n = evm.stack_pop()?;
+    // the intermediate product is not subject to the 2^256 modulo
+    let result = if n.is_zero() {
+        U256::zero()
+    } else {
+        let product = a.full_mul(b);
+        U256::try_from(product % U512::from(n)).expect("remainder is less than n")
+    };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
+}
 
-    worldstate.set_storage(&addr, key, new_value);
+fn op_exp(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let base = evm.stack_pop()?;
+    let exponent = evm.stack_pop()?;
+    let (result, _overflow) = base.overflowing_pow(exponent);
+    evm.stack_push(result)?;
     Ok(Bytes::new())
 }
 
-fn op_push_n<const N: usize>(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
-    let start = evm.pc + 1;
-    let end = start + N;
-    evm.pc += N;
-    
-    let mut buf = [0u8; 32];
-    if start < context.code.len() { // write a big-endian number
-        let actual_end = std::cmp::min(end, context.code.len());
-        let actual_len = actual_end - start;
-        buf[32 - actual_len..].copy_from_slice(&context.code[start..actual_end]);
-    }
+fn gas_exp(evm: &Machine, _context: &Context) -> Result<U256, EvmError> {
+    // G_expbyte (EIP-160) per significant byte of the exponent
+    let exponent = evm.stack_peek(1)?;
+    let byte_len = exponent.bits().div_ceil(8);
+    Ok(U256::from(50 * byte_len as u64))
+}
 
-    evm.stack_push(U256::from_big_endian(&buf))?;
+fn op_signextend(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let byte_index = evm.stack_pop()?;
+    let value = evm.stack_pop()?;
+    let result = if byte_index < U256::from(31) {
+        // extend the sign bit of the (byte_index + 1) lowest bytes
+        let sign_bit = byte_index.as_usize() * 8 + 7;
+        let mask = (U256::one() << sign_bit) - U256::one();
+        if value.bit(sign_bit) { value | !mask } else { value & mask }
+    } else {
+        value
+    };
+    evm.stack_push(result)?;
     Ok(Bytes::new())
 }
 
-fn op_push1(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<1>(evm, context, worldstate, substate)
+fn bool_to_u256(value: bool) -> U256 {
+    if value { U256::one() } else { U256::zero() }
 }
-fn op_push2(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<2>(evm, context, worldstate, substate)
+
+// signed "less than" on two's complement words
+fn signed_lt(a: U256, b: U256) -> bool {
+    match (is_negative(a), is_negative(b)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => a < b, // same sign: the unsigned order is the signed order
+    }
 }
-fn op_push3(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<3>(evm, context, worldstate, substate)
+
+fn op_lt(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    evm.stack_push(bool_to_u256(a < b))?;
+    Ok(Bytes::new())
 }
-fn op_push4(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<4>(evm, context, worldstate, substate)
+
+fn op_gt(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    evm.stack_push(bool_to_u256(a > b))?;
+    Ok(Bytes::new())
 }
-fn op_push5(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<5>(evm, context, worldstate, substate)
+
+fn op_slt(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    evm.stack_push(bool_to_u256(signed_lt(a, b)))?;
+    Ok(Bytes::new())
 }
-fn op_push6(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<6>(evm, context, worldstate, substate)
+
+fn op_sgt(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    evm.stack_push(bool_to_u256(signed_lt(b, a)))?;
+    Ok(Bytes::new())
 }
-fn op_push7(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<7>(evm, context, worldstate, substate)
+
+fn op_eq(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    evm.stack_push(bool_to_u256(a == b))?;
+    Ok(Bytes::new())
 }
-fn op_push8(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<8>(evm, context, worldstate, substate)
+
+fn op_iszero(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    evm.stack_push(bool_to_u256(a.is_zero()))?;
+    Ok(Bytes::new())
 }
-fn op_push9(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<9>(evm, context, worldstate, substate)
+
+fn op_and(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    evm.stack_push(a & b)?;
+    Ok(Bytes::new())
 }
-fn op_push10(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<10>(evm, context, worldstate, substate)
+
+fn op_or(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    evm.stack_push(a | b)?;
+    Ok(Bytes::new())
 }
-fn op_push11(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<11>(evm, context, worldstate, substate)
+
+fn op_xor(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    let b = evm.stack_pop()?;
+    evm.stack_push(a ^ b)?;
+    Ok(Bytes::new())
 }
-fn op_push12(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<12>(evm, context, worldstate, substate)
+
+fn op_not(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let a = evm.stack_pop()?;
+    evm.stack_push(!a)?;
+    Ok(Bytes::new())
 }
-fn op_push13(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<13>(evm, context, worldstate, substate)
+
+fn op_byte(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let index = evm.stack_pop()?;
+    let value = evm.stack_pop()?;
+    // index 0 is the most significant byte
+    let result = if index < U256::from(32) {
+        U256::from(value.byte(31 - index.as_usize()))
+    } else {
+        U256::zero()
+    };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
 }
-fn op_push14(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<14>(evm, context, worldstate, substate)
+
+// EIP-145: the shift amount is on top of the stack, followed by the value
+fn op_shl(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let shift = evm.stack_pop()?;
+    let value = evm.stack_pop()?;
+    let result = if shift < U256::from(256) { value << shift.as_usize() } else { U256::zero() };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
 }
-fn op_push15(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<15>(evm, context, worldstate, substate)
+
+fn op_shr(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let shift = evm.stack_pop()?;
+    let value = evm.stack_pop()?;
+    let result = if shift < U256::from(256) { value >> shift.as_usize() } else { U256::zero() };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
 }
-fn op_push16(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<16>(evm, context, worldstate, substate)
+
+fn op_sar(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let shift = evm.stack_pop()?;
+    let value = evm.stack_pop()?;
+    let negative = is_negative(value);
+    let result = if shift >= U256::from(256) {
+        if negative { U256::MAX } else { U256::zero() }
+    } else if negative {
+        // shift in ones from the left
+        !((!value) >> shift.as_usize())
+    } else {
+        value >> shift.as_usize()
+    };
+    evm.stack_push(result)?;
+    Ok(Bytes::new())
 }
-fn op_push17(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<17>(evm, context, worldstate, substate)
+
+/* ---------------------------------- keccak ---------------------------------- */
+fn op_keccak256(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    let data = read_memory(evm, offset, size);
+    let hash = H256::from_slice(&Keccak256::digest(&data));
+    if let Some(preimages) = substate.preimages.as_mut() {
+        preimages.push((hash, data.to_vec()));
+    }
+    evm.stack_push(U256::from_big_endian(hash.as_bytes()))?;
+    Ok(Bytes::new())
 }
-fn op_push18(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<18>(evm, context, worldstate, substate)
+
+fn gas_keccak256(evm: &Machine, _context: &Context) -> Result<U256, EvmError> {
+    // G_keccak256word per word, the size is bounded by memory_size_keccak256
+    let size = evm.stack_peek(1)?;
+    if size > U256::from(MAX_MEMORY_SIZE) {
+        return Err(EvmError::OutOfGas);
+    }
+    Ok(U256::from(6 * size.as_u64().div_ceil(32)))
 }
-fn op_push19(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<19>(evm, context, worldstate, substate)
+
+fn memory_size_keccak256(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    memory_span(evm.stack_peek(0)?, evm.stack_peek(1)?)
 }
-fn op_push20(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<20>(evm, context, worldstate, substate)
+
+/* -------------------------------- environment ------------------------------- */
+// EIP-2929: the first access to an account in a transaction is cold
+fn charge_account_access(evm: &mut Machine, substate: &mut Substate, address: Address) -> Result<(), EvmError> {
+    let cost = if substate.access_list_accounts.contains(&address) {
+        U256::from(100u64) // G_warmaccess
+    } else {
+        substate.access_list_accounts.push(address);
+        U256::from(2600u64) // G_coldaccountaccess
+    };
+    evm.use_gas(cost)
 }
-fn op_push21(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<21>(evm, context, worldstate, substate)
+
+// EIP-161: an account is empty when it has no code, zero nonce and zero balance
+fn is_empty_account(worldstate: &WorldStateTrie, address: &Address) -> bool {
+    match worldstate.get_account(address) {
+        Some(account) => account.nonce == 0 && account.balance.is_zero() && account.code.is_empty(),
+        None => true,
+    }
 }
-fn op_push22(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<22>(evm, context, worldstate, substate)
+
+fn u256_to_address(value: U256) -> Address {
+    Address::from_slice(&value.to_big_endian()[12..])
 }
-fn op_push23(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<23>(evm, context, worldstate, substate)
+
+fn address_to_u256(address: Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
 }
-fn op_push24(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<24>(evm, context, worldstate, substate)
+
+// data[offset..offset + size], zero padded on the right where it runs past the end of data
+fn padded_slice(data: &[u8], offset: U256, size: usize) -> Vec<u8> {
+    let mut buf = vec![0u8; size];
+    if offset < U256::from(data.len()) {
+        let offset = offset.as_usize();
+        let available = std::cmp::min(size, data.len() - offset);
+        buf[..available].copy_from_slice(&data[offset..offset + available]);
+    }
+    buf
 }
-fn op_push25(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<25>(evm, context, worldstate, substate)
+
+// shared by CALLDATACOPY, CODECOPY and EXTCODECOPY once the source is known
+fn copy_to_memory(evm: &mut Machine, source: &[u8], dest_offset: U256, offset: U256, size: U256) {
+    if size.is_zero() {
+        return;
+    }
+    let data = padded_slice(source, offset, size.as_usize());
+    evm.memory_write(dest_offset.as_usize(), &data);
 }
-fn op_push26(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<26>(evm, context, worldstate, substate)
+
+fn op_address(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let addr = context.contract_addr.unwrap_or(context.sender);
+    evm.stack_push(address_to_u256(addr))?;
+    Ok(Bytes::new())
 }
-fn op_push27(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<27>(evm, context, worldstate, substate)
+
+fn op_balance(evm: &mut Machine, _context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let addr = u256_to_address(evm.stack_pop()?);
+    charge_account_access(evm, substate, addr)?;
+    evm.stack_push(worldstate.get_balance(&addr).unwrap_or_default())?;
+    Ok(Bytes::new())
 }
-fn op_push28(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<28>(evm, context, worldstate, substate)
+
+fn op_origin(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(address_to_u256(context.origin_sender))?;
+    Ok(Bytes::new())
 }
-fn op_push29(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<29>(evm, context, worldstate, substate)
+
+fn op_caller(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(address_to_u256(context.sender))?;
+    Ok(Bytes::new())
 }
-fn op_push30(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<30>(evm, context, worldstate, substate)
+
+fn op_callvalue(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(context.value)?;
+    Ok(Bytes::new())
 }
-fn op_push31(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<31>(evm, context, worldstate, substate)
+
+fn op_calldataload(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let offset = evm.stack_pop()?;
+    // calldata not long enough, pad right with 0
+    let word = padded_slice(&context.input, offset, 32);
+    evm.stack_push(U256::from_big_endian(&word))?;
+    Ok(Bytes::new())
 }
-fn op_push32(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    op_push_n::<32>(evm, context, worldstate, substate)
+
+fn op_calldatasize(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(U256::from(context.input.len()))?;
+    Ok(Bytes::new())
 }
 
-fn op_call(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
-    let _temp_gas = evm.stack_pop()?; // Like in geth, we also do not use the gas input in stack.
-    let callee_256 = evm.stack_pop()?;
-    let callee_bytes = callee_256.to_big_endian();
-    let mut bytes20 = [0u8; 20];
-    if callee_bytes.len() >= 20 {
-        bytes20.copy_from_slice(&callee_bytes[callee_bytes.len() - 20..]);
-    } else {
-        bytes20[20 - callee_bytes.len()..].copy_from_slice(&callee_bytes);
-    }
-    let callee = Address::from_slice(&bytes20);
-    let value = evm.stack_pop()?;
-    let _in_offset = evm.stack_pop()?;
-    let _in_size = evm.stack_pop()?;
-    let _out_offset = evm.stack_pop()?;
-    let _out_size = evm.stack_pop()?;
+fn op_calldatacopy(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let dest_offset = evm.stack_pop()?;
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    copy_to_memory(evm, &context.input, dest_offset, offset, size);
+    Ok(Bytes::new())
+}
 
-    let caller = context.contract_addr.unwrap_or(context.sender);
+fn op_codesize(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(U256::from(context.code.len()))?;
+    Ok(Bytes::new())
+}
 
-    if evm.call_depth >= 1024 { // CallCreateDepth = 1024
-        return Err(EvmError::CallDepthExceeded);
-    }
+fn op_codecopy(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let dest_offset = evm.stack_pop()?;
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    copy_to_memory(evm, &context.code, dest_offset, offset, size);
+    Ok(Bytes::new())
+}
 
-    if value > worldstate.get_balance(&caller).unwrap_or(U256::zero()) {
-        return Err(EvmError::InsufficientBalance);
-    }
+// CALLDATACOPY, CODECOPY and RETURNDATACOPY: dest_offset, offset, size
+fn gas_copy(evm: &Machine, _context: &Context) -> Result<U256, EvmError> {
+    copy_gas(evm.stack_peek(2)?)
+}
 
-    // EIP-2929: cold account access cost for CALL target.
-    if !substate.access_list_accounts.contains(&callee) {
-        let cold_cost = U256::from(2600u64);
-        if cold_cost > evm.gas_remaining {
-            return Err(EvmError::OutOfGas);
-        }
-        evm.gas_remaining -= cold_cost;
-        substate.access_list_accounts.push(callee);
-    }
+fn memory_size_copy(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    memory_span(evm.stack_peek(0)?, evm.stack_peek(2)?)
+}
 
-    // Additional CALL costs for value transfer / new account creation.
-    if value > U256::zero() {
-        let transfer_cost = U256::from(9000u64);
-        if transfer_cost > evm.gas_remaining {
+// EIP-211
+fn op_returndatasize(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(U256::from(evm.return_data.len()))?;
+    Ok(Bytes::new())
+}
+
+// unlike the other copies, reading past the end of the return data is an exceptional halt
+fn op_returndatacopy(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let dest_offset = evm.stack_pop()?;
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    let (end, overflow) = offset.overflowing_add(size);
+    if overflow || end > U256::from(evm.return_data.len()) {
+        return Err(EvmError::ReturnDataOutOfBounds);
+    }
+    if size.is_zero() {
+        return Ok(Bytes::new());
+    }
+    let data = evm.return_data.slice(offset.as_usize()..end.as_usize());
+    evm.memory_write(dest_offset.as_usize(), &data);
+    Ok(Bytes::new())
+}
+
+fn op_gasprice(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(context.gas_price)?;
+    Ok(Bytes::new())
+}
+
+fn op_extcodesize(evm: &mut Machine, _context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let addr = u256_to_address(evm.stack_pop()?);
+    charge_account_access(evm, substate, addr)?;
+    let size = worldstate.get_code(&addr).map(|code| code.len()).unwrap_or(0);
+    evm.stack_push(U256::from(size))?;
+    Ok(Bytes::new())
+}
+
+fn op_extcodecopy(evm: &mut Machine, _context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let addr = u256_to_address(evm.stack_pop()?);
+    let dest_offset = evm.stack_pop()?;
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    charge_account_access(evm, substate, addr)?;
+    let code = worldstate.get_code(&addr).unwrap_or_default();
+    copy_to_memory(evm, &code, dest_offset, offset, size);
+    Ok(Bytes::new())
+}
+
+// EXTCODECOPY: address, dest_offset, offset, size
+fn gas_extcodecopy(evm: &Machine, _context: &Context) -> Result<U256, EvmError> {
+    copy_gas(evm.stack_peek(3)?)
+}
+
+fn memory_size_extcodecopy(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    memory_span(evm.stack_peek(1)?, evm.stack_peek(3)?)
+}
+
+// EIP-1052: 0 for accounts that are empty or do not exist
+fn op_extcodehash(evm: &mut Machine, _context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let addr = u256_to_address(evm.stack_pop()?);
+    charge_account_access(evm, substate, addr)?;
+    let hash = match worldstate.get_account(&addr) {
+        Some(account) if !is_empty_account(worldstate, &addr) => U256::from_big_endian(account.code_hash.as_bytes()),
+        _ => U256::zero(),
+    };
+    evm.stack_push(hash)?;
+    Ok(Bytes::new())
+}
+
+fn op_selfbalance(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let addr = context.contract_addr.unwrap_or(context.sender);
+    evm.stack_push(worldstate.get_balance(&addr).unwrap_or_default())?;
+    Ok(Bytes::new())
+}
+
+/* ------------------------------- block context ------------------------------ */
+// only the 256 most recent complete blocks are available
+const BLOCKHASH_WINDOW: u64 = 256;
+
+fn op_blockhash(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let number = evm.stack_pop()?;
+    let current = context.block.header.number;
+    let mut hash = U256::zero();
+    if number < U256::from(current) && U256::from(current) - number <= U256::from(BLOCKHASH_WINDOW) {
+        // the hash of block n is recorded as the parent hash of block n + 1
+        let child = number.as_u64() + 1;
+        let parent_hash = std::iter::once(context.block)
+            .chain(context.ancestors.iter().rev())
+            .take_while(|block| block.header.number >= child)
+            .find(|block| block.header.number == child)
+            .map(|block| block.header.parent_hash);
+        if let Some(parent_hash) = parent_hash {
+            hash = U256::from_big_endian(parent_hash.as_bytes());
+        }
+    }
+    evm.stack_push(hash)?;
+    Ok(Bytes::new())
+}
+
+fn op_coinbase(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(address_to_u256(context.block.header.beneficiary))?;
+    Ok(Bytes::new())
+}
+
+fn op_timestamp(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(U256::from(context.block.header.timestamp))?;
+    Ok(Bytes::new())
+}
+
+fn op_number(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(U256::from(context.block.header.number))?;
+    Ok(Bytes::new())
+}
+
+// EIP-4399: DIFFICULTY now returns the beacon chain randomness
+// DIFFICULTY before the merge
+fn op_prevrandao(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    if !context.spec.is_enabled_in(SpecId::Paris) {
+        evm.stack_push(context.block.header.difficulty)?;
+        return Ok(Bytes::new());
+    }
+    evm.stack_push(U256::from_big_endian(context.block.header.prev_randao.as_bytes()))?;
+    Ok(Bytes::new())
+}
+
+fn op_gaslimit(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(context.block.header.gas_limit)?;
+    Ok(Bytes::new())
+}
+
+// EIP-1344
+fn op_chainid(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(U256::from(CHAIN_ID))?;
+    Ok(Bytes::new())
+}
+
+// EIP-3198
+fn op_basefee(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(context.block.header.base_fee.unwrap_or_default())?;
+    Ok(Bytes::new())
+}
+
+// EIP-4844: 0 when the index is out of range
+fn op_blobhash(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let index = evm.stack_pop()?;
+    let hash = if index < U256::from(context.blob_hashes.len()) {
+        U256::from_big_endian(context.blob_hashes[index.as_usize()].as_bytes())
+    } else {
+        U256::zero()
+    };
+    evm.stack_push(hash)?;
+    Ok(Bytes::new())
+}
+
+// EIP-7516
+fn op_blobbasefee(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(context.block.get_base_fee_per_blob_gas(context.spec))?;
+    Ok(Bytes::new())
+}
+
+/* ---------------------------------- memory ---------------------------------- */
+// memory beyond this is never affordable: C_mem(2^27 words) alone is over 2^45 gas
+const MAX_MEMORY_SIZE: u64 = u32::MAX as u64;
+
+// the memory size required to access memory[offset..offset + size]; an empty range needs no memory
+fn memory_span(offset: U256, size: U256) -> Result<usize, EvmError> {
+    if size.is_zero() {
+        return Ok(0);
+    }
+    let max = U256::from(MAX_MEMORY_SIZE);
+    if offset > max || size > max || offset + size > max {
+        return Err(EvmError::OutOfGas);
+    }
+    Ok((offset + size).as_usize())
+}
+
+// read a memory range that has been expanded by the operation's memory_size
+fn read_memory(evm: &Machine, offset: U256, size: U256) -> Bytes {
+    if size.is_zero() {
+        return Bytes::new();
+    }
+    evm.memory_read(offset.as_usize(), size.as_usize())
+}
+
+fn copy_gas(size: U256) -> Result<U256, EvmError> {
+    // G_copy per word
+    if size > U256::from(MAX_MEMORY_SIZE) {
+        return Err(EvmError::OutOfGas);
+    }
+    Ok(U256::from(3 * size.as_u64().div_ceil(32)))
+}
+
+fn op_mload(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let offset = evm.stack_pop()?;
+    let word = read_memory(evm, offset, U256::from(32));
+    evm.stack_push(U256::from_big_endian(&word))?;
+    Ok(Bytes::new())
+}
+
+fn memory_size_mload(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    memory_span(evm.stack_peek(0)?, U256::from(32))
+}
+
+fn op_mstore(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let offset = evm.stack_pop()?;
+    let value = evm.stack_pop()?;
+    evm.memory_write(offset.as_usize(), &value.to_big_endian());
+    Ok(Bytes::new())
+}
+
+fn memory_size_mstore(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    memory_span(evm.stack_peek(0)?, U256::from(32))
+}
+
+fn op_mstore8(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let offset = evm.stack_pop()?;
+    let value = evm.stack_pop()?;
+    evm.memory_write(offset.as_usize(), &[value.byte(0)]);
+    Ok(Bytes::new())
+}
+
+fn memory_size_mstore8(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    memory_span(evm.stack_peek(0)?, U256::one())
+}
+
+fn op_msize(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(U256::from(evm.memory.len()))?;
+    Ok(Bytes::new())
+}
+
+// EIP-5656
+fn op_mcopy(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let dest = evm.stack_pop()?;
+    let src = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    if size.is_zero() {
+        return Ok(Bytes::new());
+    }
+    let (dest, src, size) = (dest.as_usize(), src.as_usize(), size.as_usize());
+    // copy_within handles overlapping ranges as if through an intermediate buffer
+    evm.memory.copy_within(src..src + size, dest);
+    Ok(Bytes::new())
+}
+
+fn gas_mcopy(evm: &Machine, _context: &Context) -> Result<U256, EvmError> {
+    copy_gas(evm.stack_peek(2)?)
+}
+
+fn memory_size_mcopy(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    let size = evm.stack_peek(2)?;
+    let dest_span = memory_span(evm.stack_peek(0)?, size)?;
+    let src_span = memory_span(evm.stack_peek(1)?, size)?;
+    Ok(dest_span.max(src_span))
+}
+
+// the key of a storage slot in Substate::access_list_storage
+fn storage_slot(addr: Address, key: U256) -> (Address, H256) {
+    (addr, H256::from(key.to_big_endian()))
+}
+
+fn op_sload(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let key = evm.stack_pop()?;
+    let addr = context.contract_addr.unwrap_or(context.sender);
+
+    // EIP-2929: 2100 for the first access of a slot in the transaction, 100 afterwards
+    let slot = storage_slot(addr, key);
+    let cost = if substate.access_list_storage.contains(&slot) {
+        U256::from(100u64) // G_warmaccess
+    } else {
+        substate.access_list_storage.push(slot);
+        U256::from(2100u64) // G_coldsload
+    };
+    evm.use_gas(cost)?;
+
+    evm.stack_push(worldstate.get_storage(&addr, key).unwrap_or_default())?;
+    Ok(Bytes::new())
+}
+
+// EIP-1153: transient storage is always warm and discarded at the end of the transaction
+fn op_tload(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let key = evm.stack_pop()?;
+    let addr = context.contract_addr.unwrap_or(context.sender);
+    evm.stack_push(worldstate.get_transient_storage(&addr, key))?;
+    Ok(Bytes::new())
+}
+
+fn op_tstore(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    if !context.allow_writes {
+        return Err(EvmError::WriteProtection);
+    }
+    let key = evm.stack_pop()?;
+    let value = evm.stack_pop()?;
+    let addr = context.contract_addr.unwrap_or(context.sender);
+    worldstate.set_transient_storage(&addr, key, value);
+    Ok(Bytes::new())
+}
+
+fn op_sstore(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    if !context.allow_writes {
+        return Err(EvmError::WriteProtection);
+    }
+    // 1. Gas Stipend 检查 (EIP-2200)
+    if evm.gas_remaining <= U256::from(2300u64) {
+        return Err(EvmError::OutOfGas);
+    }
+
+    let key = evm.stack_pop()?;
+    let new_value = evm.stack_pop()?;
+    let addr = context.contract_addr.unwrap_or(context.sender);
+
+    let current_value = worldstate.get_storage(&addr, key).unwrap_or(U256::zero());
+    let original_value = worldstate.get_original_storage(&addr, key).unwrap_or(U256::zero());
+
+    // 2. EIP-2929 Cold storage access cost
+    let slot = storage_slot(addr, key);
+    if !substate.access_list_storage.contains(&slot) {
+        let cold_cost = U256::from(2100u64);
+        if cold_cost > evm.gas_remaining {
             return Err(EvmError::OutOfGas);
         }
-        evm.gas_remaining -= transfer_cost;
+        evm.gas_remaining -= cold_cost;
+        substate.access_list_storage.push(slot);
+    }
+
+    // 3. EIP-2200 / EIP-2929 
+    let sstore_cost: u64;
+    // SSTORE_CLEARS_SCHEDULE, lowered by EIP-3529
+    let clear_refund = if context.spec.is_enabled_in(SpecId::London) { 4_800u64 } else { 15_000u64 };
+
+    if current_value == new_value {
+        sstore_cost = 100; // SLOAD_GAS (Warm)
+    } else {
+        if original_value == current_value { // Clean slot
+            if original_value.is_zero() { 
+                sstore_cost = 20_000; // SSTORE_SET_GAS
+            } else {
+                sstore_cost = 2_900;  // SSTORE_RESET_GAS (5000 - 2100)
+                if new_value.is_zero() {
+                    substate.refund_fee += U256::from(clear_refund);
+                }
+            }
+        } else { // Dirty slot 
+            sstore_cost = 100; // SLOAD_GAS (Warm)
+
+            if !original_value.is_zero() {
+                if current_value.is_zero() {
+                    substate.refund_fee -= U256::from(clear_refund);
+                }
+                if new_value.is_zero() {
+                    substate.refund_fee += U256::from(clear_refund);
+                }
+            }
+            if original_value == new_value {
+                if original_value.is_zero() {
+                    substate.refund_fee += U256::from(20_000u64 - 100u64);
+                } else {
+                    substate.refund_fee += U256::from(2_900u64 - 100u64);
+                }
+            }
+        }
+    }
+
+    let sstore_cost_u256 = U256::from(sstore_cost);
+    if sstore_cost_u256 > evm.gas_remaining {
+        return Err(EvmError::OutOfGas);
+    }
+    evm.gas_remaining -= sstore_cost_u256;
+
+    worldstate.set_storage(&addr, key, new_value);
+    Ok(Bytes::new())
+}
+
+/* ------------------------------------ log ----------------------------------- */
+// LOGn: offset, size, then n topics
+fn op_log_n<const N: usize>(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    if !context.allow_writes {
+        return Err(EvmError::WriteProtection);
+    }
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    let mut topics = Vec::with_capacity(N);
+    for _ in 0..N {
+        topics.push(H256::from(evm.stack_pop()?.to_big_endian()));
+    }
+    let data = read_memory(evm, offset, size);
+    substate.logs.push(Log {
+        address: context.contract_addr.unwrap_or(context.sender),
+        topics,
+        data,
+    });
+    Ok(Bytes::new())
+}
+
+fn gas_log(evm: &Machine, _context: &Context) -> Result<U256, EvmError> {
+    // G_logdata per byte, the size is bounded by memory_size_log
+    let size = evm.stack_peek(1)?;
+    if size > U256::from(MAX_MEMORY_SIZE) {
+        return Err(EvmError::OutOfGas);
+    }
+    Ok(size * U256::from(8u64))
+}
+
+fn memory_size_log(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    memory_span(evm.stack_peek(0)?, evm.stack_peek(1)?)
+}
+
+/* ------------------------------- return/revert ------------------------------ */
+fn op_return(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    Err(EvmError::Return(read_memory(evm, offset, size)))
+}
+
+// EIP-140: like RETURN, but the state changes are reverted and the remaining gas is kept
+fn op_revert(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    Err(EvmError::Revert(read_memory(evm, offset, size)))
+}
+
+// RETURN and REVERT: offset, size
+fn memory_size_return(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    memory_span(evm.stack_peek(0)?, evm.stack_peek(1)?)
+}
+
+/* ------------------------------- control flow ------------------------------- */
+/// Mark every JUMPDEST byte of `code` that is an opcode, skipping the immediate data of PUSH1..PUSH32.
+pub fn analyze_jumpdests(code: &[u8]) -> JumpdestBitmap {
+    let mut bitmap = JumpdestBitmap::with_len(code.len());
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        if opcode == opcodes::JUMPDEST {
+            bitmap.set(pc);
+        } else if (opcodes::PUSH1..=opcodes::PUSH32).contains(&opcode) {
+            pc += (opcode - opcodes::PUSH1 + 1) as usize;
+        }
+        pc += 1;
+    }
+    bitmap
+}
+
+// the analysis is done once per code hash, and shared by every call into the same code
+fn jumpdests(context: &Context, worldstate: &mut WorldStateTrie) -> Arc<JumpdestBitmap> {
+    if let Some(bitmap) = worldstate.get_jumpdests(context.code_hash) {
+        return bitmap;
+    }
+    let bitmap = Arc::new(analyze_jumpdests(&context.code));
+    worldstate.put_jumpdests(context.code_hash, bitmap.clone());
+    bitmap
+}
+
+fn jump_to(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, dest: U256) -> Result<(), EvmError> {
+    if dest >= U256::from(context.code.len()) || !jumpdests(context, worldstate).contains(dest.as_usize()) {
+        return Err(EvmError::InvalidJump);
+    }
+    // pc will be increased by the interpreter loop
+    evm.pc = dest.as_usize().wrapping_sub(1);
+    Ok(())
+}
+
+fn op_jump(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let dest = evm.stack_pop()?;
+    jump_to(evm, context, worldstate, dest)?;
+    Ok(Bytes::new())
+}
+
+fn op_jumpi(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let dest = evm.stack_pop()?;
+    let condition = evm.stack_pop()?;
+    if !condition.is_zero() {
+        jump_to(evm, context, worldstate, dest)?;
+    }
+    Ok(Bytes::new())
+}
+
+fn op_jumpdest(_evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    Ok(Bytes::new())
+}
+
+fn op_pc(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(U256::from(evm.pc))?;
+    Ok(Bytes::new())
+}
+
+fn op_gas(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    // the remaining gas after paying for this instruction
+    evm.stack_push(evm.gas_remaining)?;
+    Ok(Bytes::new())
+}
+
+fn op_push_n<const N: usize>(evm: &mut Machine, context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let start = evm.pc + 1;
+    let end = start + N;
+    evm.pc += N;
+    
+    let mut buf = [0u8; 32];
+    if start < context.code.len() { // write a big-endian number
+        let actual_end = std::cmp::min(end, context.code.len());
+        let actual_len = actual_end - start;
+        buf[32 - actual_len..].copy_from_slice(&context.code[start..actual_end]);
+    }
+
+    evm.stack_push(U256::from_big_endian(&buf))?;
+    Ok(Bytes::new())
+}
+
+
+fn op_pop(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_pop()?;
+    Ok(Bytes::new())
+}
+
+// EIP-3855
+fn op_push0(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    evm.stack_push(U256::zero())?;
+    Ok(Bytes::new())
+}
+
+// DUPn: duplicate the n-th stack item (1 is the top)
+fn op_dup_n<const N: usize>(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let value = evm.stack_peek(N - 1)?;
+    evm.stack_push(value)?;
+    Ok(Bytes::new())
+}
+
+// SWAPn: exchange the top item with the (n+1)-th item
+fn op_swap_n<const N: usize>(evm: &mut Machine, _context: &Context, _worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
+    let len = evm.stack.len();
+    if len <= N {
+        return Err(EvmError::StackUnderflow);
+    }
+    evm.stack.swap(len - 1, len - 1 - N);
+    Ok(Bytes::new())
+}
+
+// the parameters of a message call frame
+struct CallFrame {
+    caller: Address,       // CALLER in the callee
+    address: Address,      // the account whose storage and balance the callee works on
+    code_address: Address, // the account whose code runs
+    value: U256,           // CALLVALUE in the callee
+    transfer: bool,        // whether value moves from caller to address
+    input: Bytes,
+    gas: U256,
+    allow_writes: bool,
+}
+
+// EIP-150: the callee gets at most all but one 64th of the gas left after the call costs
+fn call_gas(evm: &mut Machine, requested: U256) -> Result<U256, EvmError> {
+    let available = evm.gas_remaining - evm.gas_remaining / 64;
+    let gas = requested.min(available);
+    evm.use_gas(gas)?;
+    Ok(gas)
+}
+
+fn op_call(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let gas = evm.stack_pop()?;
+    let callee = u256_to_address(evm.stack_pop()?);
+    let value = evm.stack_pop()?;
+    let in_offset = evm.stack_pop()?;
+    let in_size = evm.stack_pop()?;
+    let out_offset = evm.stack_pop()?;
+    let out_size = evm.stack_pop()?;
+    if !context.allow_writes && !value.is_zero() {
+        return Err(EvmError::WriteProtection);
+    }
+
+    // EIP-2929: warm/cold account access cost for CALL target.
+    charge_account_access(evm, substate, callee)?;
+
+    // Additional CALL costs for value transfer / new account creation.
+    if !value.is_zero() {
+        evm.use_gas(U256::from(9000u64))?; // G_callvalue
+        if is_empty_account(worldstate, &callee) {
+            evm.use_gas(U256::from(25_000u64))?; // G_newaccount
+        }
+    }
+
+    let mut callee_gas = call_gas(evm, gas)?;
+    if !value.is_zero() {
+        callee_gas += U256::from(2300u64); // G_callstipend, free for the caller
+    }
+
+    let caller = context.contract_addr.unwrap_or(context.sender);
+    let frame = CallFrame {
+        caller,
+        address: callee,
+        code_address: callee,
+        value,
+        transfer: true,
+        input: read_memory(evm, in_offset, in_size),
+        gas: callee_gas,
+        allow_writes: context.allow_writes,
+    };
+    execute_call(evm, context, worldstate, substate, frame, out_offset, out_size)
+}
+
+// run the code of another account on the storage and balance of the current one
+fn op_callcode(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let gas = evm.stack_pop()?;
+    let code_address = u256_to_address(evm.stack_pop()?);
+    let value = evm.stack_pop()?;
+    let in_offset = evm.stack_pop()?;
+    let in_size = evm.stack_pop()?;
+    let out_offset = evm.stack_pop()?;
+    let out_size = evm.stack_pop()?;
+
+    charge_account_access(evm, substate, code_address)?;
+    // the value stays in the current account, so there is never a new account to pay for
+    if !value.is_zero() {
+        evm.use_gas(U256::from(9000u64))?; // G_callvalue
+    }
+    let mut callee_gas = call_gas(evm, gas)?;
+    if !value.is_zero() {
+        callee_gas += U256::from(2300u64); // G_callstipend
+    }
+
+    let address = context.contract_addr.unwrap_or(context.sender);
+    let frame = CallFrame {
+        caller: address,
+        address,
+        code_address,
+        value,
+        transfer: true,
+        input: read_memory(evm, in_offset, in_size),
+        gas: callee_gas,
+        allow_writes: context.allow_writes,
+    };
+    execute_call(evm, context, worldstate, substate, frame, out_offset, out_size)
+}
+
+// EIP-7: like CALLCODE, but CALLER and CALLVALUE are kept from the current frame
+fn op_delegatecall(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let gas = evm.stack_pop()?;
+    let code_address = u256_to_address(evm.stack_pop()?);
+    let in_offset = evm.stack_pop()?;
+    let in_size = evm.stack_pop()?;
+    let out_offset = evm.stack_pop()?;
+    let out_size = evm.stack_pop()?;
+
+    charge_account_access(evm, substate, code_address)?;
+    let callee_gas = call_gas(evm, gas)?;
+
+    let frame = CallFrame {
+        caller: context.sender,
+        address: context.contract_addr.unwrap_or(context.sender),
+        code_address,
+        value: context.value,
+        transfer: false,
+        input: read_memory(evm, in_offset, in_size),
+        gas: callee_gas,
+        allow_writes: context.allow_writes,
+    };
+    execute_call(evm, context, worldstate, substate, frame, out_offset, out_size)
+}
+
+// EIP-214: a CALL without value whose whole call tree cannot modify the state
+fn op_staticcall(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    let gas = evm.stack_pop()?;
+    let callee = u256_to_address(evm.stack_pop()?);
+    let in_offset = evm.stack_pop()?;
+    let in_size = evm.stack_pop()?;
+    let out_offset = evm.stack_pop()?;
+    let out_size = evm.stack_pop()?;
+
+    charge_account_access(evm, substate, callee)?;
+    let callee_gas = call_gas(evm, gas)?;
+
+    let frame = CallFrame {
+        caller: context.contract_addr.unwrap_or(context.sender),
+        address: callee,
+        code_address: callee,
+        value: U256::zero(),
+        transfer: false,
+        input: read_memory(evm, in_offset, in_size),
+        gas: callee_gas,
+        allow_writes: false,
+    };
+    execute_call(evm, context, worldstate, substate, frame, out_offset, out_size)
+}
+
+// run a message call frame and push 1 on success, 0 on failure
+fn execute_call(
+    evm: &mut Machine,
+    context: &Context,
+    worldstate: &mut WorldStateTrie,
+    substate: &mut Substate,
+    frame: CallFrame,
+    out_offset: U256,
+    out_size: U256,
+) -> ExecuteResult {
+    evm.return_data = Bytes::new();
+
+    // the call does not happen, the gas given to it goes back to the caller
+    let balance = worldstate.get_balance(&frame.caller).unwrap_or_default();
+    if evm.call_depth >= 1024 || (frame.transfer && frame.value > balance) {
+        evm.gas_remaining += frame.gas;
+        evm.stack_push(U256::zero())?;
+        return Ok(Bytes::new());
+    }
+
+    worldstate.checkpoint();
+    let substate_checkpoint = substate.checkpoint();
+
+    if frame.transfer && !frame.value.is_zero() {
+        if !worldstate.account_exists(&frame.address) {
+            worldstate.insert(&frame.address, AccountState::default());
+        }
+        let caller_balance = worldstate.get_balance(&frame.caller).unwrap_or_default();
+        worldstate.set_balance(&frame.caller, caller_balance - frame.value);
+        let callee_balance = worldstate.get_balance(&frame.address).unwrap_or_default();
+        worldstate.set_balance(&frame.address, callee_balance + frame.value);
+    }
+
+    let code_account = worldstate.get_account(&frame.code_address).unwrap_or_default();
+    let callee_context = Context {
+        contract_addr: Some(frame.address),
+        origin_sender: context.origin_sender,
+        gas_price: context.gas_price,
+        input: frame.input,
+        sender: frame.caller,
+        value: frame.value,
+        code: code_account.code,
+        code_hash: code_account.code_hash,
+        block: context.block,
+        ancestors: context.ancestors,
+        blob_hashes: context.blob_hashes.clone(),
+        spec: context.spec,
+        precompiles: context.precompiles,
+        depth: context.depth + 1,
+        allow_writes: frame.allow_writes,
+    };
+    let mut callee_evm = Machine {
+        memory: vec![],
+        stack: vec![],
+        pc: 0,
+        gas_remaining: frame.gas,
+        call_depth: evm.call_depth + 1,
+        return_data: Bytes::new(),
+    };
+
+    let result = if let Some(precompile) = context.precompiles.get(&frame.code_address) {
+        run_precompile(precompile, &mut callee_evm, &callee_context)
+    } else {
+        callee_evm.run(&callee_context, worldstate, substate)
+    };
+
+    match result {
+        Ok(output) => {
+            worldstate.commit();
+            evm.gas_remaining += callee_evm.gas_remaining;
+            write_call_output(evm, out_offset, out_size, output);
+            evm.stack_push(U256::one())?;
+        }
+        Err(e) => {
+            let _ = worldstate.rollback();
+            substate.rollback(substate_checkpoint);
+            match e {
+                // REVERT keeps the unused gas and still hands its output to the caller
+                EvmError::Revert(output) => {
+                    evm.gas_remaining += callee_evm.gas_remaining;
+                    write_call_output(evm, out_offset, out_size, output);
+                }
+                EvmError::Fatal(_) => return Err(e),
+                _ => {}
+            }
+            evm.stack_push(U256::zero())?;
+        }
+    }
+    Ok(Bytes::new())
+}
+
+// a failing precompile consumes all the gas given to it
+pub(crate) fn run_precompile(precompile: &dyn Precompile, evm: &mut Machine, context: &Context) -> ExecuteResult {
+    let gas_cost = precompile.gas_cost(evm, context);
+    let result = evm.use_gas(gas_cost).and_then(|_| precompile.execute(evm, context));
+    match result {
+        Ok(output) => Ok(output.unwrap_or_default()),
+        Err(e) => {
+            evm.gas_remaining = U256::zero();
+            Err(e)
+        }
+    }
+}
+
+// keep the callee output as return data and copy at most out_size bytes of it to memory[out_offset..]
+fn write_call_output(evm: &mut Machine, out_offset: U256, out_size: U256, output: Bytes) {
+    if !out_size.is_zero() {
+        let len = out_size.min(U256::from(output.len())).as_usize();
+        evm.memory_write(out_offset.as_usize(), &output[..len]);
+    }
+    evm.return_data = output;
+}
+
+// CALL and CALLCODE: gas, address, value, in_offset, in_size, out_offset, out_size
+fn memory_size_call(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    let in_span = memory_span(evm.stack_peek(3)?, evm.stack_peek(4)?)?;
+    let out_span = memory_span(evm.stack_peek(5)?, evm.stack_peek(6)?)?;
+    Ok(in_span.max(out_span))
+}
+
+// DELEGATECALL and STATICCALL: gas, address, in_offset, in_size, out_offset, out_size
+fn memory_size_call_no_value(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    let in_span = memory_span(evm.stack_peek(2)?, evm.stack_peek(3)?)?;
+    let out_span = memory_span(evm.stack_peek(4)?, evm.stack_peek(5)?)?;
+    Ok(in_span.max(out_span))
+}
+
+fn op_create(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    if !context.allow_writes {
+        return Err(EvmError::WriteProtection);
+    }
+    let value = evm.stack_pop()?;
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    let init_code = read_memory(evm, offset, size);
+    create_contract(evm, context, worldstate, substate, value, init_code, None)
+}
+
+// EIP-1014: the address depends on the salt and the init code instead of the nonce
+fn op_create2(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    if !context.allow_writes {
+        return Err(EvmError::WriteProtection);
+    }
+    let value = evm.stack_pop()?;
+    let offset = evm.stack_pop()?;
+    let size = evm.stack_pop()?;
+    let salt = H256::from(evm.stack_pop()?.to_big_endian());
+    let init_code = read_memory(evm, offset, size);
+    create_contract(evm, context, worldstate, substate, value, init_code, Some(salt))
+}
+
+// run the init code in a child frame and deposit the code it returns, pushing the new address or 0
+fn create_contract(
+    evm: &mut Machine,
+    context: &Context,
+    worldstate: &mut WorldStateTrie,
+    substate: &mut Substate,
+    value: U256,
+    init_code: Bytes,
+    salt: Option<H256>,
+) -> ExecuteResult {
+    evm.return_data = Bytes::new();
+    let caller = context.contract_addr.unwrap_or(context.sender);
+
+    // the creation does not happen and costs nothing beyond the constant and memory gas
+    let balance = worldstate.get_balance(&caller).unwrap_or_default();
+    let nonce = worldstate.get_nonce(&caller).unwrap_or(0);
+    if evm.call_depth >= 1024 || value > balance || nonce == u64::MAX {
+        evm.stack_push(U256::zero())?;
+        return Ok(Bytes::new());
+    }
+
+    let address = create_address(caller, nonce, salt, Some(&init_code));
+    worldstate.set_nonce(&caller, nonce + 1);
+    if !substate.access_list_accounts.contains(&address) {
+        substate.access_list_accounts.push(address);
+    }
+
+    // EIP-150: all but one 64th of the remaining gas goes to the init code
+    let callee_gas = call_gas(evm, evm.gas_remaining)?;
+
+    // an account with code or nonce already lives at the address, the gas given to it is lost
+    if let Some(existing) = worldstate.get_account(&address) {
+        if existing.nonce != 0 || !existing.code.is_empty() {
+            evm.stack_push(U256::zero())?;
+            return Ok(Bytes::new());
+        }
+    }
+
+    worldstate.checkpoint();
+    let substate_checkpoint = substate.checkpoint();
+
+    // EIP-161: new contracts start with nonce 1, a balance sent there earlier is kept
+    let existing_balance = worldstate.get_balance(&address).unwrap_or_default();
+    let account = AccountState { nonce: 1, balance: existing_balance, ..Default::default() };
+    worldstate.insert(&address, account);
+    substate.created_accounts.push(address);
+    if !value.is_zero() {
+        let caller_balance = worldstate.get_balance(&caller).unwrap_or_default();
+        worldstate.set_balance(&caller, caller_balance - value);
+        worldstate.set_balance(&address, existing_balance + value);
+    }
+
+    let callee_context = Context {
+        contract_addr: Some(address),
+        origin_sender: context.origin_sender,
+        gas_price: context.gas_price,
+        input: Bytes::new(), // init code has no calldata
+        sender: caller,
+        value,
+        code_hash: H256::from_slice(&Keccak256::digest(&init_code)),
+        code: init_code.to_vec(),
+        block: context.block,
+        ancestors: context.ancestors,
+        blob_hashes: context.blob_hashes.clone(),
+        spec: context.spec,
+        precompiles: context.precompiles,
+        depth: context.depth + 1,
+        allow_writes: true,
+    };
+    let mut callee_evm = Machine {
+        memory: vec![],
+        stack: vec![],
+        pc: 0,
+        gas_remaining: callee_gas,
+        call_depth: evm.call_depth + 1,
+        return_data: Bytes::new(),
+    };
+
+    let result = callee_evm.run(&callee_context, worldstate, substate)
+        .and_then(|code| deposit_code(&mut callee_evm, worldstate, context.spec, address, code));
+
+    match result {
+        Ok(()) => {
+            worldstate.commit();
+            evm.gas_remaining += callee_evm.gas_remaining;
+            evm.stack_push(address_to_u256(address))?;
+        }
+        Err(e) => {
+            let _ = worldstate.rollback();
+            substate.rollback(substate_checkpoint);
+            match e {
+                // only a reverting init code hands its output back as return data
+                EvmError::Revert(output) => {
+                    evm.gas_remaining += callee_evm.gas_remaining;
+                    evm.return_data = output;
+                }
+                EvmError::Fatal(_) => return Err(e),
+                _ => {}
+            }
+            evm.stack_push(U256::zero())?;
+        }
+    }
+    Ok(Bytes::new())
+}
+
+// store the code returned by the init code, any failure here consumes all the gas of the frame
+fn deposit_code(evm: &mut Machine, worldstate: &mut WorldStateTrie, spec: SpecId, address: Address, code: Bytes) -> Result<(), EvmError> {
+    let result = if code.len() > MAX_CODE_SIZE {
+        Err(EvmError::OutOfGas)
+    } else if code.first() == Some(&0xEF) && spec.is_enabled_in(SpecId::London) {
+        // EIP-3541: reserved for the EVM object format
+        Err(EvmError::InvalidOpcode)
+    } else {
+        evm.use_gas(U256::from(G_CODE_DEPOSIT) * U256::from(code.len()))
+    };
+    if result.is_err() {
+        evm.gas_remaining = U256::zero();
+    }
+    result?;
+    worldstate.set_code(&address, code.to_vec());
+    Ok(())
+}
+
+// EIP-3860: G_initcodeword per word of init code
+fn gas_create(evm: &Machine, context: &Context) -> Result<U256, EvmError> {
+    let size = evm.stack_peek(2)?;
+    if !context.spec.is_enabled_in(SpecId::Shanghai) {
+        return Ok(U256::zero());
+    }
+    if size > U256::from(MAX_INITCODE_SIZE) {
+        return Err(EvmError::InitCodeSizeExceeded);
+    }
+    Ok(U256::from(2 * size.as_usize().div_ceil(32)))
+}
+
+// CREATE2 also hashes the init code: G_keccak256word per word
+fn gas_create2(evm: &Machine, context: &Context) -> Result<U256, EvmError> {
+    let size = evm.stack_peek(2)?;
+    if size > U256::from(MAX_MEMORY_SIZE) {
+        return Err(EvmError::OutOfGas);
+    }
+    Ok(gas_create(evm, context)? + U256::from(6 * size.as_usize().div_ceil(32)))
+}
+
+// CREATE and CREATE2: value, offset, size
+fn memory_size_create(evm: &Machine, _context: &Context) -> Result<usize, EvmError> {
+    memory_span(evm.stack_peek(1)?, evm.stack_peek(2)?)
+}
+
+// EIP-6780: the account is only deleted when it was created in the same transaction
+fn op_selfdestruct(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, substate: &mut Substate) -> ExecuteResult {
+    if !context.allow_writes {
+        return Err(EvmError::WriteProtection);
+    }
+    let beneficiary = u256_to_address(evm.stack_pop()?);
+    let address = context.contract_addr.unwrap_or(context.sender);
+
+    // EIP-2929: only a cold beneficiary costs extra, there is no warm access charge
+    if !substate.access_list_accounts.contains(&beneficiary) {
+        substate.access_list_accounts.push(beneficiary);
+        evm.use_gas(U256::from(2600u64))?; // G_coldaccountaccess
+    }
+    let balance = worldstate.get_balance(&address).unwrap_or_default();
+    if !balance.is_zero() && is_empty_account(worldstate, &beneficiary) {
+        evm.use_gas(U256::from(25000u64))?; // G_newaccount
+    }
+
+    // before EIP-6780 every SELFDESTRUCT deletes the account
+    let destroyed = !context.spec.is_enabled_in(SpecId::Cancun) || substate.created_accounts.contains(&address);
+    if destroyed && !context.spec.is_enabled_in(SpecId::London) && !substate.self_destruct.contains(&address) {
+        substate.refund_fee += U256::from(24000u64); // R_selfdestruct, removed by EIP-3529
+    }
+    if beneficiary != address && !balance.is_zero() {
+        if !worldstate.account_exists(&beneficiary) {
+            worldstate.insert(&beneficiary, AccountState::default());
+        }
+        let beneficiary_balance = worldstate.get_balance(&beneficiary).unwrap_or_default();
+        worldstate.set_balance(&beneficiary, beneficiary_balance + balance);
+        worldstate.set_balance(&address, U256::zero());
+    } else if destroyed {
+        // sending the balance to itself burns it when the account goes away
+        worldstate.set_balance(&address, U256::zero());
+    }
+    if destroyed && !substate.self_destruct.contains(&address) {
+        substate.self_destruct.push(address);
+    }
+    Err(EvmError::SelfDestruct)
+}
+
+static CANCUN_JUMP_TABLE: Lazy<JumpTable> = Lazy::new(|| {
+    let mut table = HashMap::new();
+    let push_ops: [ExecutionFunc; 32] = [
+        op_push_n::<1>, op_push_n::<2>, op_push_n::<3>, op_push_n::<4>,
+        op_push_n::<5>, op_push_n::<6>, op_push_n::<7>, op_push_n::<8>,
+        op_push_n::<9>, op_push_n::<10>, op_push_n::<11>, op_push_n::<12>,
+        op_push_n::<13>, op_push_n::<14>, op_push_n::<15>, op_push_n::<16>,
+        op_push_n::<17>, op_push_n::<18>, op_push_n::<19>, op_push_n::<20>,
+        op_push_n::<21>, op_push_n::<22>, op_push_n::<23>, op_push_n::<24>,
+        op_push_n::<25>, op_push_n::<26>, op_push_n::<27>, op_push_n::<28>,
+        op_push_n::<29>, op_push_n::<30>, op_push_n::<31>, op_push_n::<32>,
+    ];
+    let dup_ops: [ExecutionFunc; 16] = [
+        op_dup_n::<1>, op_dup_n::<2>, op_dup_n::<3>, op_dup_n::<4>,
+        op_dup_n::<5>, op_dup_n::<6>, op_dup_n::<7>, op_dup_n::<8>,
+        op_dup_n::<9>, op_dup_n::<10>, op_dup_n::<11>, op_dup_n::<12>,
+        op_dup_n::<13>, op_dup_n::<14>, op_dup_n::<15>, op_dup_n::<16>,
+    ];
+    let log_ops: [ExecutionFunc; 5] = [
+        op_log_n::<0>, op_log_n::<1>, op_log_n::<2>, op_log_n::<3>, op_log_n::<4>,
+    ];
+    let swap_ops: [ExecutionFunc; 16] = [
+        op_swap_n::<1>, op_swap_n::<2>, op_swap_n::<3>, op_swap_n::<4>,
+        op_swap_n::<5>, op_swap_n::<6>, op_swap_n::<7>, op_swap_n::<8>,
+        op_swap_n::<9>, op_swap_n::<10>, op_swap_n::<11>, op_swap_n::<12>,
+        op_swap_n::<13>, op_swap_n::<14>, op_swap_n::<15>, op_swap_n::<16>,
+    ];
+
+    // STOP
+    table.insert(
+        opcodes::STOP,
+        Operation::new(
+            opcodes::STOP,
+            op_stop,
+            0,     // constant gas (STOP has 0 gas cost)
+            None,  // no dynamic gas
+            0,     // min stack (no stack operations)
+            max_stack(0, 0),
+            None,  // no memory size
+        ),
+    );
+
+    // ADD
+    table.insert(
+        opcodes::ADD,
+        Operation::new(
+            opcodes::ADD,
+            op_add,
+            3,     // constant gas
+            None,  // no dynamic gas
+            2,     // min stack
+            max_stack(2, 1),
+            None,  // no memory size
+        ),
+    );
+
+    // MUL
+    table.insert(
+        opcodes::MUL,
+        Operation::new(opcodes::MUL, op_mul, 5, None, 2, max_stack(2, 1), None),
+    );
+
+    // SUB
+    table.insert(
+        opcodes::SUB,
+        Operation::new(opcodes::SUB, op_sub, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // DIV
+    table.insert(
+        opcodes::DIV,
+        Operation::new(opcodes::DIV, op_div, 5, None, 2, max_stack(2, 1), None),
+    );
+
+    // SDIV
+    table.insert(
+        opcodes::SDIV,
+        Operation::new(opcodes::SDIV, op_sdiv, 5, None, 2, max_stack(2, 1), None),
+    );
+
+    // MOD
+    table.insert(
+        opcodes::MOD,
+        Operation::new(opcodes::MOD, op_mod, 5, None, 2, max_stack(2, 1), None),
+    );
+
+    // SMOD
+    table.insert(
+        opcodes::SMOD,
+        Operation::new(opcodes::SMOD, op_smod, 5, None, 2, max_stack(2, 1), None),
+    );
+
+    // ADDMOD
+    table.insert(
+        opcodes::ADDMOD,
+        Operation::new(opcodes::ADDMOD, op_addmod, 8, None, 3, max_stack(3, 1), None),
+    );
+
+    // MULMOD
+    table.insert(
+        opcodes::MULMOD,
+        Operation::new(opcodes::MULMOD, op_mulmod, 8, None, 3, max_stack(3, 1), None),
+    );
+
+    // EXP
+    table.insert(
+        opcodes::EXP,
+        Operation::new(
+            opcodes::EXP,
+            op_exp,
+            10,            // constant gas
+            Some(gas_exp), // 50 per byte of the exponent
+            2,
+            max_stack(2, 1),
+            None,
+        ),
+    );
+
+    // SIGNEXTEND
+    table.insert(
+        opcodes::SIGNEXTEND,
+        Operation::new(opcodes::SIGNEXTEND, op_signextend, 5, None, 2, max_stack(2, 1), None),
+    );
+
+    // LT
+    table.insert(
+        opcodes::LT,
+        Operation::new(opcodes::LT, op_lt, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // GT
+    table.insert(
+        opcodes::GT,
+        Operation::new(opcodes::GT, op_gt, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // SLT
+    table.insert(
+        opcodes::SLT,
+        Operation::new(opcodes::SLT, op_slt, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // SGT
+    table.insert(
+        opcodes::SGT,
+        Operation::new(opcodes::SGT, op_sgt, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // EQ
+    table.insert(
+        opcodes::EQ,
+        Operation::new(opcodes::EQ, op_eq, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // ISZERO
+    table.insert(
+        opcodes::ISZERO,
+        Operation::new(opcodes::ISZERO, op_iszero, 3, None, 1, max_stack(1, 1), None),
+    );
+
+    // AND
+    table.insert(
+        opcodes::AND,
+        Operation::new(opcodes::AND, op_and, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // OR
+    table.insert(
+        opcodes::OR,
+        Operation::new(opcodes::OR, op_or, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // XOR
+    table.insert(
+        opcodes::XOR,
+        Operation::new(opcodes::XOR, op_xor, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // NOT
+    table.insert(
+        opcodes::NOT,
+        Operation::new(opcodes::NOT, op_not, 3, None, 1, max_stack(1, 1), None),
+    );
+
+    // BYTE
+    table.insert(
+        opcodes::BYTE,
+        Operation::new(opcodes::BYTE, op_byte, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // SHL
+    table.insert(
+        opcodes::SHL,
+        Operation::new(opcodes::SHL, op_shl, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // SHR
+    table.insert(
+        opcodes::SHR,
+        Operation::new(opcodes::SHR, op_shr, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // SAR
+    table.insert(
+        opcodes::SAR,
+        Operation::new(opcodes::SAR, op_sar, 3, None, 2, max_stack(2, 1), None),
+    );
+
+    // KECCAK256
+    table.insert(
+        opcodes::KECCAK256,
+        Operation::new(
+            opcodes::KECCAK256,
+            op_keccak256,
+            30,
+            Some(gas_keccak256),
+            2,
+            max_stack(2, 1),
+            Some(memory_size_keccak256),
+        ),
+    );
+
+    // ADDRESS
+    table.insert(
+        opcodes::ADDRESS,
+        Operation::new(opcodes::ADDRESS, op_address, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // BALANCE: warm/cold access is charged in op_balance
+    table.insert(
+        opcodes::BALANCE,
+        Operation::new(opcodes::BALANCE, op_balance, 0, None, 1, max_stack(1, 1), None),
+    );
+
+    // ORIGIN
+    table.insert(
+        opcodes::ORIGIN,
+        Operation::new(opcodes::ORIGIN, op_origin, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // CALLER
+    table.insert(
+        opcodes::CALLER,
+        Operation::new(opcodes::CALLER, op_caller, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // CALLVALUE
+    table.insert(
+        opcodes::CALLVALUE,
+        Operation::new(opcodes::CALLVALUE, op_callvalue, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // CALLDATALOAD
+    table.insert(
+        opcodes::CALLDATALOAD,
+        Operation::new(
+            opcodes::CALLDATALOAD,
+            op_calldataload,
+            3,
+            None,
+            1,
+            max_stack(1, 1),
+            None,
+        ),
+    );
+
+    // CALLDATASIZE
+    table.insert(
+        opcodes::CALLDATASIZE,
+        Operation::new(opcodes::CALLDATASIZE, op_calldatasize, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // CALLDATACOPY
+    table.insert(
+        opcodes::CALLDATACOPY,
+        Operation::new(
+            opcodes::CALLDATACOPY,
+            op_calldatacopy,
+            3,
+            Some(gas_copy),
+            3,
+            max_stack(3, 0),
+            Some(memory_size_copy),
+        ),
+    );
+
+    // CODESIZE
+    table.insert(
+        opcodes::CODESIZE,
+        Operation::new(opcodes::CODESIZE, op_codesize, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // CODECOPY
+    table.insert(
+        opcodes::CODECOPY,
+        Operation::new(
+            opcodes::CODECOPY,
+            op_codecopy,
+            3,
+            Some(gas_copy),
+            3,
+            max_stack(3, 0),
+            Some(memory_size_copy),
+        ),
+    );
+
+    // GASPRICE
+    table.insert(
+        opcodes::GASPRICE,
+        Operation::new(opcodes::GASPRICE, op_gasprice, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // EXTCODESIZE: warm/cold access is charged in op_extcodesize
+    table.insert(
+        opcodes::EXTCODESIZE,
+        Operation::new(opcodes::EXTCODESIZE, op_extcodesize, 0, None, 1, max_stack(1, 1), None),
+    );
+
+    // EXTCODECOPY: warm/cold access is charged in op_extcodecopy
+    table.insert(
+        opcodes::EXTCODECOPY,
+        Operation::new(
+            opcodes::EXTCODECOPY,
+            op_extcodecopy,
+            0,
+            Some(gas_extcodecopy),
+            4,
+            max_stack(4, 0),
+            Some(memory_size_extcodecopy),
+        ),
+    );
+
+    // RETURNDATASIZE
+    table.insert(
+        opcodes::RETURNDATASIZE,
+        Operation::new(opcodes::RETURNDATASIZE, op_returndatasize, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // RETURNDATACOPY
+    table.insert(
+        opcodes::RETURNDATACOPY,
+        Operation::new(
+            opcodes::RETURNDATACOPY,
+            op_returndatacopy,
+            3,
+            Some(gas_copy),
+            3,
+            max_stack(3, 0),
+            Some(memory_size_copy),
+        ),
+    );
+
+    // EXTCODEHASH: warm/cold access is charged in op_extcodehash
+    table.insert(
+        opcodes::EXTCODEHASH,
+        Operation::new(opcodes::EXTCODEHASH, op_extcodehash, 0, None, 1, max_stack(1, 1), None),
+    );
+
+    // BLOCKHASH
+    table.insert(
+        opcodes::BLOCKHASH,
+        Operation::new(opcodes::BLOCKHASH, op_blockhash, 20, None, 1, max_stack(1, 1), None),
+    );
+
+    // COINBASE
+    table.insert(
+        opcodes::COINBASE,
+        Operation::new(opcodes::COINBASE, op_coinbase, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // TIMESTAMP
+    table.insert(
+        opcodes::TIMESTAMP,
+        Operation::new(opcodes::TIMESTAMP, op_timestamp, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // NUMBER
+    table.insert(
+        opcodes::NUMBER,
+        Operation::new(opcodes::NUMBER, op_number, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // PREVRANDAO
+    table.insert(
+        opcodes::PREVRANDAO,
+        Operation::new(opcodes::PREVRANDAO, op_prevrandao, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // GASLIMIT
+    table.insert(
+        opcodes::GASLIMIT,
+        Operation::new(opcodes::GASLIMIT, op_gaslimit, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // CHAINID
+    table.insert(
+        opcodes::CHAINID,
+        Operation::new(opcodes::CHAINID, op_chainid, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // SELFBALANCE
+    table.insert(
+        opcodes::SELFBALANCE,
+        Operation::new(opcodes::SELFBALANCE, op_selfbalance, 5, None, 0, max_stack(0, 1), None),
+    );
+
+    // BASEFEE
+    table.insert(
+        opcodes::BASEFEE,
+        Operation::new(opcodes::BASEFEE, op_basefee, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // BLOBHASH
+    table.insert(
+        opcodes::BLOBHASH,
+        Operation::new(opcodes::BLOBHASH, op_blobhash, 3, None, 1, max_stack(1, 1), None),
+    );
+
+    // BLOBBASEFEE
+    table.insert(
+        opcodes::BLOBBASEFEE,
+        Operation::new(opcodes::BLOBBASEFEE, op_blobbasefee, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // MLOAD
+    table.insert(
+        opcodes::MLOAD,
+        Operation::new(opcodes::MLOAD, op_mload, 3, None, 1, max_stack(1, 1), Some(memory_size_mload)),
+    );
+
+    // MSTORE
+    table.insert(
+        opcodes::MSTORE,
+        Operation::new(opcodes::MSTORE, op_mstore, 3, None, 2, max_stack(2, 0), Some(memory_size_mstore)),
+    );
+
+    // MSTORE8
+    table.insert(
+        opcodes::MSTORE8,
+        Operation::new(opcodes::MSTORE8, op_mstore8, 3, None, 2, max_stack(2, 0), Some(memory_size_mstore8)),
+    );
+
+    // MSIZE
+    table.insert(
+        opcodes::MSIZE,
+        Operation::new(opcodes::MSIZE, op_msize, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // MCOPY
+    table.insert(
+        opcodes::MCOPY,
+        Operation::new(
+            opcodes::MCOPY,
+            op_mcopy,
+            3,                        // constant gas
+            Some(gas_mcopy),          // 3 per copied word
+            3,                        // min stack (dest, src, size)
+            max_stack(3, 0),
+            Some(memory_size_mcopy),  // max(dest, src) + size
+        ),
+    );
+
+    // SLOAD: warm/cold access is charged in op_sload
+    table.insert(
+        opcodes::SLOAD,
+        Operation::new(opcodes::SLOAD, op_sload, 0, None, 1, max_stack(1, 1), None),
+    );
+
+    // SSTORE
+    table.insert(
+        opcodes::SSTORE,
+        Operation::new(
+            opcodes::SSTORE,
+            op_sstore,
+            0,
+            None,
+            2,
+            max_stack(2, 0),
+            None,
+        ),
+    );
+
+    // TLOAD
+    table.insert(
+        opcodes::TLOAD,
+        Operation::new(opcodes::TLOAD, op_tload, 100, None, 1, max_stack(1, 1), None),
+    );
+
+    // TSTORE
+    table.insert(
+        opcodes::TSTORE,
+        Operation::new(opcodes::TSTORE, op_tstore, 100, None, 2, max_stack(2, 0), None),
+    );
+
+    // JUMP
+    table.insert(
+        opcodes::JUMP,
+        Operation::new(opcodes::JUMP, op_jump, 8, None, 1, max_stack(1, 0), None),
+    );
+
+    // JUMPI
+    table.insert(
+        opcodes::JUMPI,
+        Operation::new(opcodes::JUMPI, op_jumpi, 10, None, 2, max_stack(2, 0), None),
+    );
+
+    // PC
+    table.insert(
+        opcodes::PC,
+        Operation::new(opcodes::PC, op_pc, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // GAS
+    table.insert(
+        opcodes::GAS,
+        Operation::new(opcodes::GAS, op_gas, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // JUMPDEST
+    table.insert(
+        opcodes::JUMPDEST,
+        Operation::new(opcodes::JUMPDEST, op_jumpdest, 1, None, 0, max_stack(0, 0), None),
+    );
+
+    // POP
+    table.insert(
+        opcodes::POP,
+        Operation::new(opcodes::POP, op_pop, 2, None, 1, max_stack(1, 0), None),
+    );
+
+    // PUSH0
+    table.insert(
+        opcodes::PUSH0,
+        Operation::new(opcodes::PUSH0, op_push0, 2, None, 0, max_stack(0, 1), None),
+    );
+
+    // PUSH1..PUSH32
+    for i in 0..32u8 {
+        let opcode = opcodes::PUSH1 + i;
+        table.insert(
+            opcode,
+            Operation::new(
+                opcode,
+                push_ops[i as usize],
+                3,
+                None,
+                0,
+                max_stack(0, 1),
+                None,
+            ),
+        );
+    }
+
+    // DUP1..DUP16
+    for i in 0..16u8 {
+        let opcode = opcodes::DUP1 + i;
+        let n = i as usize + 1;
+        table.insert(
+            opcode,
+            Operation::new(
+                opcode,
+                dup_ops[i as usize],
+                3,
+                None,
+                n,                   // min stack
+                max_stack(n, n + 1), // max stack
+                None,
+            ),
+        );
+    }
+
+    // SWAP1..SWAP16
+    for i in 0..16u8 {
+        let opcode = opcodes::SWAP1 + i;
+        let n = i as usize + 1;
+        table.insert(
+            opcode,
+            Operation::new(
+                opcode,
+                swap_ops[i as usize],
+                3,
+                None,
+                n + 1,                   // min stack
+                max_stack(n + 1, n + 1), // max stack
+                None,
+            ),
+        );
+    }
+
+    // LOG0..LOG4: G_log + G_logtopic per topic, G_logdata per byte is dynamic
+    for i in 0..5u8 {
+        let opcode = opcodes::LOG0 + i;
+        let n = i as usize;
+        table.insert(
+            opcode,
+            Operation::new(
+                opcode,
+                log_ops[n],
+                375 + 375 * n as u64,
+                Some(gas_log),
+                n + 2,               // min stack
+                max_stack(n + 2, 0), // max stack
+                Some(memory_size_log),
+            ),
+        );
+    }
+
+    // CALL
+    table.insert(
+        opcodes::CALL,
+        Operation::new(
+            opcodes::CALL,
+            op_call,
+            0,     // access, value transfer and forwarded gas are charged in op_call
+            None,
+            7,     // min stack (gas, addr, value, in_offset, in_size, out_offset, out_size)
+            max_stack(7, 1),
+            Some(memory_size_call),
+        ),
+    );
+
+    // CALLCODE
+    table.insert(
+        opcodes::CALLCODE,
+        Operation::new(
+            opcodes::CALLCODE,
+            op_callcode,
+            0,     // access, value transfer and forwarded gas are charged in op_callcode
+            None,
+            7,     // min stack (gas, addr, value, in_offset, in_size, out_offset, out_size)
+            max_stack(7, 1),
+            Some(memory_size_call),
+        ),
+    );
+
+    // DELEGATECALL
+    table.insert(
+        opcodes::DELEGATECALL,
+        Operation::new(
+            opcodes::DELEGATECALL,
+            op_delegatecall,
+            0,     // access and forwarded gas are charged in op_delegatecall
+            None,
+            6,     // min stack (gas, addr, in_offset, in_size, out_offset, out_size)
+            max_stack(6, 1),
+            Some(memory_size_call_no_value),
+        ),
+    );
+
+    // STATICCALL
+    table.insert(
+        opcodes::STATICCALL,
+        Operation::new(
+            opcodes::STATICCALL,
+            op_staticcall,
+            0,     // access and forwarded gas are charged in op_staticcall
+            None,
+            6,     // min stack (gas, addr, in_offset, in_size, out_offset, out_size)
+            max_stack(6, 1),
+            Some(memory_size_call_no_value),
+        ),
+    );
+
+    // CREATE
+    table.insert(
+        opcodes::CREATE,
+        Operation::new(
+            opcodes::CREATE,
+            op_create,
+            32000, // base gas cost
+            Some(gas_create),
+            3,     // min stack (value, offset, size)
+            max_stack(3, 1),
+            Some(memory_size_create),
+        ),
+    );
+
+    // CREATE2
+    table.insert(
+        opcodes::CREATE2,
+        Operation::new(
+            opcodes::CREATE2,
+            op_create2,
+            32000, // base gas cost
+            Some(gas_create2),
+            4,     // min stack (value, offset, size, salt)
+            max_stack(4, 1),
+            Some(memory_size_create),
+        ),
+    );
+
+    // RETURN
+    table.insert(
+        opcodes::RETURN,
+        Operation::new(opcodes::RETURN, op_return, 0, None, 2, max_stack(2, 0), Some(memory_size_return)),
+    );
+
+    // REVERT
+    table.insert(
+        opcodes::REVERT,
+        Operation::new(opcodes::REVERT, op_revert, 0, None, 2, max_stack(2, 0), Some(memory_size_return)),
+    );
+
+    // SELFDESTRUCT: cold beneficiary and new account gas are charged in op_selfdestruct
+    table.insert(
+        opcodes::SELFDESTRUCT,
+        Operation::new(opcodes::SELFDESTRUCT, op_selfdestruct, 5000, None, 1, max_stack(1, 0), None),
+    );
+
+    table
+});
+
+// the instruction set of an earlier fork is a later one without the opcodes it introduced
+fn without_opcodes(table: &JumpTable, removed: &[u8]) -> JumpTable {
+    let mut table = table.clone();
+    for opcode in removed {
+        table.remove(opcode);
+    }
+    table
+}
+
+// EIP-1153 TLOAD/TSTORE, EIP-4844 BLOBHASH, EIP-5656 MCOPY and EIP-7516 BLOBBASEFEE are Cancun opcodes
+static SHANGHAI_JUMP_TABLE: Lazy<JumpTable> = Lazy::new(|| {
+    without_opcodes(&CANCUN_JUMP_TABLE, &[
+        opcodes::TLOAD, opcodes::TSTORE, opcodes::BLOBHASH, opcodes::MCOPY, opcodes::BLOBBASEFEE,
+    ])
+});
+
+// EIP-3855
+static LONDON_JUMP_TABLE: Lazy<JumpTable> = Lazy::new(|| without_opcodes(&SHANGHAI_JUMP_TABLE, &[opcodes::PUSH0]));
+
+// EIP-3198
+static BERLIN_JUMP_TABLE: Lazy<JumpTable> = Lazy::new(|| without_opcodes(&LONDON_JUMP_TABLE, &[opcodes::BASEFEE]));
+
+/// the instruction set of a fork; the gas rules that changed within it are selected by the operations
+pub fn jump_table(spec: SpecId) -> &'static JumpTable {
+    match spec {
+        SpecId::Berlin => &BERLIN_JUMP_TABLE,
+        SpecId::London | SpecId::Paris => &LONDON_JUMP_TABLE,
+        SpecId::Shanghai => &SHANGHAI_JUMP_TABLE,
+        SpecId::Cancun | SpecId::Prague => &CANCUN_JUMP_TABLE,
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                 precompile                                 */
+/* -------------------------------------------------------------------------- */
+// Send + Sync so that the built-in registries can be shared statics
+pub trait Precompile: Send + Sync {
+    fn execute(&self, evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError>;
+    fn gas_cost(&self, evm: &Machine, context: &Context) -> U256;
+}
+
+
+// order of the secp256k1 group
+static SECP256K1_N: Lazy<U256> = Lazy::new(|| {
+    U256::from_big_endian(&hex_literal::hex!("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141"))
+});
+
+pub struct Ecrecover;
+impl Precompile for Ecrecover {
+    // input: hash, v, r, s as 32-byte words; output: the signer address left-padded to 32 bytes
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let input = padded_slice(&context.input, U256::zero(), 128);
+        let hash = H256::from_slice(&input[..32]);
+        let v = U256::from_big_endian(&input[32..64]);
+        let r = U256::from_big_endian(&input[64..96]);
+        let s = U256::from_big_endian(&input[96..128]);
+
+        // an invalid signature is not an error, it just recovers nothing
+        let n = *SECP256K1_N;
+        if (v != U256::from(27) && v != U256::from(28)) || r.is_zero() || r >= n || s.is_zero() || s >= n {
+            return Ok(None);
+        }
+        let parity = (v.low_u64() - 27) as u8;
+        // unlike transactions, high s is allowed here: (r, n - s) with the other parity recovers the same key
+        let (s, parity) = if s > n / 2 { (n - s, parity ^ 1) } else { (s, parity) };
+
+        match crypto::recover_address_from_signature_prehash(hash, r, s, parity) {
+            Ok(address) => {
+                let mut output = vec![0u8; 32];
+                output[12..].copy_from_slice(address.as_bytes());
+                Ok(Some(Bytes::from(output)))
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+        U256::from(3000)
+    }
+}
+pub struct Sha256Hash;
+impl Precompile for Sha256Hash {
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let hash = sha2::Sha256::digest(&context.input);
+        Ok(Some(Bytes::copy_from_slice(&hash)))
+    }
+
+    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
+        let l = context.input.len();
+        U256::from(60 + ((l + 31) / 32) * 12) // basegas:60, gas per word: 12
+    }
+}
+pub struct Ripemd160Hash;
+impl Precompile for Ripemd160Hash {
+    // the 20-byte digest is left-padded to 32 bytes
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let hash = ripemd::Ripemd160::digest(&context.input);
+        let mut output = vec![0u8; 32];
+        output[12..].copy_from_slice(&hash);
+        Ok(Some(Bytes::from(output)))
+    }
+
+    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
+        let l = context.input.len();
+        U256::from(600 + l.div_ceil(32) * 120) // basegas:600, gas per word: 120
+    }
+}
+pub struct DataCopy;
+impl Precompile for DataCopy {
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        Ok(Some(context.input.clone()))
+    }
+
+    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
+        let l = context.input.len();
+        U256::from(15 + ((l + 31) / 32) * 3) // basegas:15, gas per word: 3
+    }
+}
+pub struct ModExp;
+impl ModExp {
+    // base_len, exp_len and mod_len, the 32-byte header of the input
+    fn lengths(input: &[u8]) -> (U256, U256, U256) {
+        let header = padded_slice(input, U256::zero(), 96);
+        (
+            U256::from_big_endian(&header[..32]),
+            U256::from_big_endian(&header[32..64]),
+            U256::from_big_endian(&header[64..96]),
+        )
+    }
+}
+impl Precompile for ModExp {
+    // input: base_len, exp_len, mod_len, then base, exp and modulus; output: base^exp % modulus in mod_len bytes
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let (base_len, exp_len, mod_len) = Self::lengths(&context.input);
+        // the gas already paid bounds mod_len and base_len, but not exp_len when both are zero
+        if mod_len.is_zero() {
+            return Ok(None);
+        }
+        let (base_len, exp_len, mod_len) = (base_len.as_usize(), exp_len.as_usize(), mod_len.as_usize());
+        let base = padded_slice(&context.input, U256::from(96), base_len);
+        let exp = padded_slice(&context.input, U256::from(96 + base_len), exp_len);
+        let modulus = padded_slice(&context.input, U256::from(96 + base_len + exp_len), mod_len);
+
+        let result = aurora_engine_modexp::modexp(&base, &exp, &modulus);
+        let mut output = vec![0u8; mod_len];
+        output[mod_len - result.len()..].copy_from_slice(&result);
+        Ok(Some(Bytes::from(output)))
+    }
+
+    // EIP-2565: max(200, multiplication_complexity * iteration_count / 3)
+    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
+        let (base_len, exp_len, mod_len) = Self::lengths(&context.input);
+        let max_len = base_len.max(mod_len);
+        if max_len > U256::from(u32::MAX) {
+            return U256::MAX; // more than any gas limit, without allocating the operands
+        }
+        let words = max_len.as_u64().div_ceil(8);
+        if words == 0 {
+            return U256::from(200); // nothing to multiply, whatever the exponent length
+        }
+        if exp_len > U256::from(u32::MAX) {
+            return U256::MAX;
+        }
+        let multiplication_complexity = U256::from(words * words);
+
+        // the bit length of the first 32 bytes of the exponent, plus 8 bits per byte after them
+        let exp_len = exp_len.as_usize();
+        let exp_head_len = exp_len.min(32);
+        let exp_head = padded_slice(&context.input, U256::from(96) + base_len, exp_head_len);
+        let exp_head_bits = U256::from_big_endian(&exp_head).bits() as u64;
+        let iteration_count = if exp_len <= 32 {
+            exp_head_bits.saturating_sub(1)
+        } else {
+            8 * (exp_len as u64 - 32) + exp_head_bits.saturating_sub(1)
+        };
+
+        let gas = multiplication_complexity * U256::from(iteration_count.max(1)) / 3;
+        gas.max(U256::from(200))
+    }
+}
+// alt_bn128 field elements must be below the field modulus
+fn read_bn_fq(bytes: &[u8]) -> Result<bn::Fq, EvmError> {
+    bn::Fq::from_slice(bytes).map_err(|_| EvmError::ExecutionFailed)
+}
+
+// a G1 point as x, y; (0, 0) is the point at infinity, anything else must be on the curve
+fn read_bn_g1(bytes: &[u8]) -> Result<bn::G1, EvmError> {
+    use bn::Group;
+    let x = read_bn_fq(&bytes[..32])?;
+    let y = read_bn_fq(&bytes[32..64])?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(bn::G1::zero());
+    }
+    bn::AffineG1::new(x, y).map(Into::into).map_err(|_| EvmError::ExecutionFailed)
+}
+
+// a G2 point as x_imaginary, x_real, y_imaginary, y_real; it must also be in the subgroup
+fn read_bn_g2(bytes: &[u8]) -> Result<bn::G2, EvmError> {
+    use bn::Group;
+    let x = bn::Fq2::new(read_bn_fq(&bytes[32..64])?, read_bn_fq(&bytes[..32])?);
+    let y = bn::Fq2::new(read_bn_fq(&bytes[96..128])?, read_bn_fq(&bytes[64..96])?);
+    if x.is_zero() && y.is_zero() {
+        return Ok(bn::G2::zero());
+    }
+    bn::AffineG2::new(x, y).map(Into::into).map_err(|_| EvmError::ExecutionFailed)
+}
+
+fn encode_bn_g1(point: bn::G1) -> Bytes {
+    let mut output = vec![0u8; 64];
+    if let Some(affine) = bn::AffineG1::from_jacobian(point) {
+        // 32-byte slices always fit a field element
+        affine.x().to_big_endian(&mut output[..32]).unwrap();
+        affine.y().to_big_endian(&mut output[32..]).unwrap();
+    }
+    Bytes::from(output)
+}
+
+pub struct EcAdd;
+impl Precompile for EcAdd {
+    // input: two G1 points; output: their sum
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let input = padded_slice(&context.input, U256::zero(), 128);
+        let sum = read_bn_g1(&input[..64])? + read_bn_g1(&input[64..])?;
+        Ok(Some(encode_bn_g1(sum)))
+    }
+
+    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+        U256::from(150) // EIP-1108
+    }
+}
+pub struct EcMul;
+impl Precompile for EcMul {
+    // input: a G1 point and a scalar; output: the point multiplied by the scalar
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let input = padded_slice(&context.input, U256::zero(), 96);
+        let point = read_bn_g1(&input[..64])?;
+        let scalar = bn::Fr::from_slice(&input[64..]).map_err(|_| EvmError::ExecutionFailed)?;
+        Ok(Some(encode_bn_g1(point * scalar)))
+    }
+
+    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+        U256::from(6000) // EIP-1108
+    }
+}
+pub struct EcPairing;
+impl Precompile for EcPairing {
+    // input: pairs of a G1 and a G2 point; output: 1 if the product of their pairings is one, else 0
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        if !context.input.len().is_multiple_of(192) {
+            return Err(EvmError::ExecutionFailed);
+        }
+        let pairs = context.input.chunks(192)
+            .map(|pair| Ok((read_bn_g1(&pair[..64])?, read_bn_g2(&pair[64..])?)))
+            .collect::<Result<Vec<_>, EvmError>>()?;
+        let mut output = vec![0u8; 32];
+        if bn::pairing_batch(&pairs) == bn::Gt::one() {
+            output[31] = 1;
+        }
+        Ok(Some(Bytes::from(output)))
+    }
+
+    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
+        let k = context.input.len() / 192;
+        U256::from(45000 + k * 34000) // EIP-1108, basegas:45000, gas per pair: 34000
+    }
+}
+const BLAKE2B_IV: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+const BLAKE2B_SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+// the BLAKE2b mixing function G
+fn blake2b_mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+// the BLAKE2b compression function F with a configurable number of rounds (RFC 7693)
+fn blake2b_compress(rounds: u32, h: &mut [u64; 8], m: &[u64; 16], t: [u64; 2], last_block: bool) {
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&BLAKE2B_IV);
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    if last_block {
+        v[14] = !v[14];
+    }
+    for round in 0..rounds as usize {
+        let s = &BLAKE2B_SIGMA[round % 10];
+        blake2b_mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        blake2b_mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        blake2b_mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        blake2b_mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        blake2b_mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        blake2b_mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        blake2b_mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        blake2b_mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+const BLAKE2F_INPUT_LENGTH: usize = 213;
+
+pub struct Blake2F;
+impl Precompile for Blake2F {
+    // EIP-152 input: rounds (4 bytes big-endian), h (64), m (128), t (16), all little-endian words, and f (1)
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let input = &context.input;
+        if input.len() != BLAKE2F_INPUT_LENGTH {
+            return Err(EvmError::ExecutionFailed);
+        }
+        let last_block = match input[212] {
+            0 => false,
+            1 => true,
+            _ => return Err(EvmError::ExecutionFailed),
+        };
+        let word = |offset: usize| u64::from_le_bytes(input[offset..offset + 8].try_into().unwrap());
+        let rounds = u32::from_be_bytes(input[..4].try_into().unwrap());
+        let mut h: [u64; 8] = std::array::from_fn(|i| word(4 + i * 8));
+        let m: [u64; 16] = std::array::from_fn(|i| word(68 + i * 8));
+        let t = [word(196), word(204)];
+
+        blake2b_compress(rounds, &mut h, &m, t, last_block);
+        Ok(Some(h.iter().flat_map(|x| x.to_le_bytes()).collect()))
+    }
+
+    // one gas per round, a malformed input fails in execute
+    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
+        if context.input.len() != BLAKE2F_INPUT_LENGTH {
+            return U256::zero();
+        }
+        U256::from(u32::from_be_bytes(context.input[..4].try_into().unwrap()))
+    }
+}
+pub struct PointEvaluation;
+impl Precompile for PointEvaluation {
+    // EIP-4844 input: versioned_hash, z, y, commitment (48 bytes), proof (48 bytes)
+    // output: FIELD_ELEMENTS_PER_BLOB and BLS_MODULUS as 32-byte words
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let input = &context.input;
+        if input.len() != 192 {
+            return Err(EvmError::ExecutionFailed);
+        }
+        let (versioned_hash, z, y) = (&input[..32], &input[32..64], &input[64..96]);
+        let (commitment, proof) = (&input[96..144], &input[144..192]);
+        if crypto::kzg_to_versioned_hash(commitment).as_bytes() != versioned_hash {
+            return Err(EvmError::ExecutionFailed);
+        }
+        match crypto::verify_kzg_proof(commitment, z, y, proof) {
+            Ok(true) => {}
+            Ok(false) => return Err(EvmError::ExecutionFailed),
+            Err(e) => return Err(EvmError::Fatal(e.to_string())),
+        }
+
+        let mut output = vec![0u8; 64];
+        output[24..32].copy_from_slice(&kzg::FIELD_ELEMENTS_PER_BLOB.to_be_bytes());
+        output[32..].copy_from_slice(&kzg::BLS_MODULUS);
+        Ok(Some(Bytes::from(output)))
+    }
+
+    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+        U256::from(50000)
+    }
+}
+// BLS12-381 base field modulus p, every encoded coordinate must be below it
+const BLS12_381_FP_MODULUS: [u8; 48] = hex_literal::hex!(
+    "1a0111ea397fe69a4b1ba7b6434bacd764774b84f38512bf6730d2a0f6b0f6241eabfffeb153ffffb9feffffffffaaab"
+);
+const BLS12_381_FP_LENGTH: usize = 64;
+const BLS12_381_G1_LENGTH: usize = 128;
+const BLS12_381_G2_LENGTH: usize = 256;
+const BLS12_381_SCALAR_LENGTH: usize = 32;
+
+// EIP-2537 MSM discounts in parts per thousand for k = 1..=128 pairs, more pairs use the last entry
+const BLS12_381_G1_MSM_DISCOUNT: [u64; 128] = [
+    1000, 949, 848, 797, 764, 750, 738, 728, 719, 712, 705, 698, 692, 687, 682, 677,
+    673, 669, 665, 661, 658, 654, 651, 648, 645, 642, 640, 637, 635, 632, 630, 627,
+    625, 623, 621, 619, 617, 615, 613, 611, 609, 608, 606, 604, 603, 601, 599, 598,
+    596, 595, 593, 592, 591, 589, 588, 586, 585, 584, 582, 581, 580, 579, 577, 576,
+    575, 574, 573, 572, 570, 569, 568, 567, 566, 565, 564, 563, 562, 561, 560, 559,
+    558, 557, 556, 555, 554, 553, 552, 551, 550, 549, 548, 547, 547, 546, 545, 544,
+    543, 542, 541, 540, 540, 539, 538, 537, 536, 536, 535, 534, 533, 532, 532, 531,
+    530, 529, 528, 528, 527, 526, 525, 525, 524, 523, 522, 522, 521, 520, 520, 519,
+];
+const BLS12_381_G2_MSM_DISCOUNT: [u64; 128] = [
+    1000, 1000, 923, 884, 855, 832, 812, 796, 782, 770, 759, 749, 740, 732, 724, 717,
+    711, 704, 699, 693, 688, 683, 679, 674, 670, 666, 663, 659, 655, 652, 649, 646,
+    643, 640, 637, 634, 632, 629, 627, 624, 622, 620, 618, 615, 613, 611, 609, 607,
+    606, 604, 602, 600, 598, 597, 595, 593, 592, 590, 589, 587, 586, 584, 583, 582,
+    580, 579, 578, 576, 575, 574, 573, 571, 570, 569, 568, 567, 566, 565, 563, 562,
+    561, 560, 559, 558, 557, 556, 555, 554, 553, 552, 552, 551, 550, 549, 548, 547,
+    546, 545, 545, 544, 543, 542, 541, 541, 540, 539, 538, 537, 537, 536, 535, 535,
+    534, 533, 532, 532, 531, 530, 530, 529, 528, 528, 527, 526, 526, 525, 524, 524,
+];
+
+// an Fp element is 16 zero bytes followed by its 48-byte big-endian value
+fn read_bls_fp(bytes: &[u8]) -> Result<blst::blst_fp, EvmError> {
+    let (padding, value) = bytes.split_at(BLS12_381_FP_LENGTH - 48);
+    if padding.iter().any(|&b| b != 0) || value >= &BLS12_381_FP_MODULUS[..] {
+        return Err(EvmError::ExecutionFailed);
+    }
+    let mut fp = blst::blst_fp::default();
+    // value is exactly 48 bytes
+    unsafe { blst::blst_fp_from_bendian(&mut fp, value.as_ptr()) };
+    Ok(fp)
+}
+
+// an Fp2 element c0 + c1 * u as c0, c1
+fn read_bls_fp2(bytes: &[u8]) -> Result<blst::blst_fp2, EvmError> {
+    Ok(blst::blst_fp2 {
+        fp: [read_bls_fp(&bytes[..BLS12_381_FP_LENGTH])?, read_bls_fp(&bytes[BLS12_381_FP_LENGTH..])?],
+    })
+}
+
+// a G1 point as x, y; all zeros is the point at infinity.
+// addition only needs the point on the curve, MSM and pairing also need it in the subgroup
+fn read_bls_g1(bytes: &[u8], subgroup_check: bool) -> Result<blst::blst_p1_affine, EvmError> {
+    let point = blst::blst_p1_affine {
+        x: read_bls_fp(&bytes[..BLS12_381_FP_LENGTH])?,
+        y: read_bls_fp(&bytes[BLS12_381_FP_LENGTH..BLS12_381_G1_LENGTH])?,
+    };
+    let valid = unsafe {
+        blst::blst_p1_affine_on_curve(&point) && (!subgroup_check || blst::blst_p1_affine_in_g1(&point))
+    };
+    if !valid {
+        return Err(EvmError::ExecutionFailed);
+    }
+    Ok(point)
+}
+
+// a G2 point as x, y with Fp2 coordinates, with the same checks as G1
+fn read_bls_g2(bytes: &[u8], subgroup_check: bool) -> Result<blst::blst_p2_affine, EvmError> {
+    let point = blst::blst_p2_affine {
+        x: read_bls_fp2(&bytes[..2 * BLS12_381_FP_LENGTH])?,
+        y: read_bls_fp2(&bytes[2 * BLS12_381_FP_LENGTH..BLS12_381_G2_LENGTH])?,
+    };
+    let valid = unsafe {
+        blst::blst_p2_affine_on_curve(&point) && (!subgroup_check || blst::blst_p2_affine_in_g2(&point))
+    };
+    if !valid {
+        return Err(EvmError::ExecutionFailed);
+    }
+    Ok(point)
+}
+
+// scalars are 32-byte big-endian integers, they are not reduced by the group order
+fn read_bls_scalar(bytes: &[u8]) -> blst::blst_scalar {
+    let mut scalar = blst::blst_scalar::default();
+    // bytes is exactly 32 bytes
+    unsafe { blst::blst_scalar_from_bendian(&mut scalar, bytes.as_ptr()) };
+    scalar
+}
+
+fn encode_bls_fp(output: &mut [u8], fp: &blst::blst_fp) {
+    unsafe { blst::blst_bendian_from_fp(output[BLS12_381_FP_LENGTH - 48..].as_mut_ptr(), fp) };
+}
+
+// the point at infinity is encoded as all zeros
+fn encode_bls_g1(point: &blst::blst_p1) -> Bytes {
+    let mut affine = blst::blst_p1_affine::default();
+    unsafe { blst::blst_p1_to_affine(&mut affine, point) };
+    let mut output = vec![0u8; BLS12_381_G1_LENGTH];
+    encode_bls_fp(&mut output[..BLS12_381_FP_LENGTH], &affine.x);
+    encode_bls_fp(&mut output[BLS12_381_FP_LENGTH..], &affine.y);
+    Bytes::from(output)
+}
+
+fn encode_bls_g2(point: &blst::blst_p2) -> Bytes {
+    let mut affine = blst::blst_p2_affine::default();
+    unsafe { blst::blst_p2_to_affine(&mut affine, point) };
+    let mut output = vec![0u8; BLS12_381_G2_LENGTH];
+    let coordinates = [&affine.x.fp[0], &affine.x.fp[1], &affine.y.fp[0], &affine.y.fp[1]];
+    for (chunk, fp) in output.chunks_mut(BLS12_381_FP_LENGTH).zip(coordinates) {
+        encode_bls_fp(chunk, fp);
+    }
+    Bytes::from(output)
+}
+
+// k * multiplication cost * discount(k) / 1000
+fn bls_msm_gas(k: usize, multiplication_cost: u64, discounts: &[u64; 128]) -> U256 {
+    if k == 0 {
+        return U256::zero();
+    }
+    let discount = discounts[k.min(discounts.len()) - 1];
+    U256::from(k) * U256::from(multiplication_cost * discount) / 1000
+}
+
+pub struct Bls12G1Add;
+impl Precompile for Bls12G1Add {
+    // input: two G1 points; output: their sum
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let input = &context.input;
+        if input.len() != 2 * BLS12_381_G1_LENGTH {
+            return Err(EvmError::ExecutionFailed);
+        }
+        let a = read_bls_g1(&input[..BLS12_381_G1_LENGTH], false)?;
+        let b = read_bls_g1(&input[BLS12_381_G1_LENGTH..], false)?;
+        let mut sum = blst::blst_p1::default();
+        unsafe {
+            blst::blst_p1_from_affine(&mut sum, &a);
+            blst::blst_p1_add_or_double_affine(&mut sum, &sum, &b);
+        }
+        Ok(Some(encode_bls_g1(&sum)))
+    }
 
-        if !worldstate.account_exists(&callee) {
-            let new_account_cost = U256::from(25_000u64);
-            if new_account_cost > evm.gas_remaining {
-                return Err(EvmError::OutOfGas);
+    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+        U256::from(375)
+    }
+}
+pub struct Bls12G1Msm;
+impl Precompile for Bls12G1Msm {
+    // input: pairs of a G1 point and a scalar; output: the sum of the points multiplied by their scalars
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        use blst::MultiPoint;
+        let pair_length = BLS12_381_G1_LENGTH + BLS12_381_SCALAR_LENGTH;
+        let input = &context.input;
+        if input.is_empty() || !input.len().is_multiple_of(pair_length) {
+            return Err(EvmError::ExecutionFailed);
+        }
+        let mut points = Vec::new();
+        let mut scalars = Vec::new();
+        for pair in input.chunks(pair_length) {
+            let point = read_bls_g1(&pair[..BLS12_381_G1_LENGTH], true)?;
+            // the point at infinity adds nothing to the sum
+            if unsafe { blst::blst_p1_affine_is_inf(&point) } {
+                continue;
             }
-            evm.gas_remaining -= new_account_cost;
+            points.push(point);
+            scalars.extend_from_slice(&read_bls_scalar(&pair[BLS12_381_G1_LENGTH..]).b);
         }
+        if points.is_empty() {
+            return Ok(Some(Bytes::from(vec![0u8; BLS12_381_G1_LENGTH])));
+        }
+        Ok(Some(encode_bls_g1(&points.as_slice().mult(&scalars, 256))))
     }
 
-    // Check precompile
-    if is_precompile(&callee) {
-        let precompiled_contracts = precompiled_contracts_berlin();
-        let precompile = precompiled_contracts.get(&callee).unwrap();
-        let gas_cost = precompile.gas_cost(evm, context);
-
-        if gas_cost > evm.gas_remaining {
-            return Err(EvmError::OutOfGas);
+    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
+        let k = context.input.len() / (BLS12_381_G1_LENGTH + BLS12_381_SCALAR_LENGTH);
+        bls_msm_gas(k, 12000, &BLS12_381_G1_MSM_DISCOUNT)
+    }
+}
+pub struct Bls12G2Add;
+impl Precompile for Bls12G2Add {
+    // input: two G2 points; output: their sum
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let input = &context.input;
+        if input.len() != 2 * BLS12_381_G2_LENGTH {
+            return Err(EvmError::ExecutionFailed);
         }
-        evm.gas_remaining -= gas_cost;
-        let _output = precompile.execute(evm, context)?;
-        // Push success (1) to stack
-        evm.stack_push(U256::from(1))?;
-        return Ok(Bytes::new());
+        let a = read_bls_g2(&input[..BLS12_381_G2_LENGTH], false)?;
+        let b = read_bls_g2(&input[BLS12_381_G2_LENGTH..], false)?;
+        let mut sum = blst::blst_p2::default();
+        unsafe {
+            blst::blst_p2_from_affine(&mut sum, &a);
+            blst::blst_p2_add_or_double_affine(&mut sum, &sum, &b);
+        }
+        Ok(Some(encode_bls_g2(&sum)))
     }
 
-    // Create account if needed (EIP-158)
-    if !worldstate.account_exists(&callee) {
-        if value == U256::zero() {
-            evm.stack_push(U256::zero())?; // Return 0 for failure
-            return Ok(Bytes::new());
+    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+        U256::from(600)
+    }
+}
+pub struct Bls12G2Msm;
+impl Precompile for Bls12G2Msm {
+    // input: pairs of a G2 point and a scalar; output: the sum of the points multiplied by their scalars
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        use blst::MultiPoint;
+        let pair_length = BLS12_381_G2_LENGTH + BLS12_381_SCALAR_LENGTH;
+        let input = &context.input;
+        if input.is_empty() || !input.len().is_multiple_of(pair_length) {
+            return Err(EvmError::ExecutionFailed);
         }
-        let account = AccountState::new(&vec![]);
-        worldstate.insert(&callee, account);
+        let mut points = Vec::new();
+        let mut scalars = Vec::new();
+        for pair in input.chunks(pair_length) {
+            let point = read_bls_g2(&pair[..BLS12_381_G2_LENGTH], true)?;
+            if unsafe { blst::blst_p2_affine_is_inf(&point) } {
+                continue;
+            }
+            points.push(point);
+            scalars.extend_from_slice(&read_bls_scalar(&pair[BLS12_381_G2_LENGTH..]).b);
+        }
+        if points.is_empty() {
+            return Ok(Some(Bytes::from(vec![0u8; BLS12_381_G2_LENGTH])));
+        }
+        Ok(Some(encode_bls_g2(&points.as_slice().mult(&scalars, 256))))
     }
 
-    // Transfer value
-    if value > U256::zero() {
-        let caller_balance = worldstate.get_balance(&caller).unwrap_or(U256::zero());
-        let callee_balance = worldstate.get_balance(&callee).unwrap_or(U256::zero());
-        worldstate.set_balance(&caller, caller_balance - value);
-        worldstate.set_balance(&callee, callee_balance + value);
+    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
+        let k = context.input.len() / (BLS12_381_G2_LENGTH + BLS12_381_SCALAR_LENGTH);
+        bls_msm_gas(k, 22500, &BLS12_381_G2_MSM_DISCOUNT)
+    }
+}
+pub struct Bls12Pairing;
+impl Precompile for Bls12Pairing {
+    // input: pairs of a G1 and a G2 point; output: 1 if the product of their pairings is one, else 0
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        let pair_length = BLS12_381_G1_LENGTH + BLS12_381_G2_LENGTH;
+        let input = &context.input;
+        if input.is_empty() || !input.len().is_multiple_of(pair_length) {
+            return Err(EvmError::ExecutionFailed);
+        }
+        let mut product = blst::blst_fp12::default();
+        for (i, pair) in input.chunks(pair_length).enumerate() {
+            let p = read_bls_g1(&pair[..BLS12_381_G1_LENGTH], true)?;
+            let q = read_bls_g2(&pair[BLS12_381_G1_LENGTH..], true)?;
+            let mut miller_loop = blst::blst_fp12::default();
+            unsafe {
+                blst::blst_miller_loop(&mut miller_loop, &q, &p);
+                if i == 0 {
+                    product = miller_loop;
+                } else {
+                    blst::blst_fp12_mul(&mut product, &product, &miller_loop);
+                }
+            }
+        }
+        let mut result = blst::blst_fp12::default();
+        let mut output = vec![0u8; 32];
+        unsafe {
+            blst::blst_final_exp(&mut result, &product);
+            if blst::blst_fp12_is_one(&result) {
+                output[31] = 1;
+            }
+        }
+        Ok(Some(Bytes::from(output)))
     }
 
-    // Get callee code
-    let callee_code = worldstate.get_code(&callee).unwrap_or_default();
-    
-    // Create new context for callee
-    let callee_context = Context {
-        contract_addr: Some(callee),
-        origin_sender: context.origin_sender,
-        gas_price: context.gas_price,
-        input: context.input.clone(),
-        sender: caller,
-        value,
-        code: callee_code.clone(),
-        block: context.block,
-        depth: context.depth + 1,
-        allow_writes: true,
-    };
+    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
+        let k = context.input.len() / (BLS12_381_G1_LENGTH + BLS12_381_G2_LENGTH);
+        U256::from(37700 + k * 32600)
+    }
+}
+pub struct Bls12MapFpToG1;
+impl Precompile for Bls12MapFpToG1 {
+    // input: an Fp element; output: the G1 point it maps to (simplified SWU and cofactor clearing)
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        if context.input.len() != BLS12_381_FP_LENGTH {
+            return Err(EvmError::ExecutionFailed);
+        }
+        let u = read_bls_fp(&context.input)?;
+        let mut point = blst::blst_p1::default();
+        unsafe { blst::blst_map_to_g1(&mut point, &u, std::ptr::null()) };
+        Ok(Some(encode_bls_g1(&point)))
+    }
 
-    // Create new machine for callee
-    let mut callee_evm = Machine {
-        memory: Bytes::new(),
-        stack: vec![],
-        pc: 0,
-        gas_remaining: evm.gas_remaining,
-        call_depth: evm.call_depth + 1,
-    };
+    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+        U256::from(5500)
+    }
+}
+pub struct Bls12MapFp2ToG2;
+impl Precompile for Bls12MapFp2ToG2 {
+    // input: an Fp2 element; output: the G2 point it maps to
+    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+        if context.input.len() != 2 * BLS12_381_FP_LENGTH {
+            return Err(EvmError::ExecutionFailed);
+        }
+        let u = read_bls_fp2(&context.input)?;
+        let mut point = blst::blst_p2::default();
+        unsafe { blst::blst_map_to_g2(&mut point, &u, std::ptr::null()) };
+        Ok(Some(encode_bls_g2(&point)))
+    }
 
-    // Execute callee
-    worldstate.checkpoint();
-    let result = callee_evm.run(&callee_context, worldstate, substate);
-    
-    if result.is_err() {
-        let _ = worldstate.rollback(); // Rollback on error
-        evm.stack_push(U256::zero())?; // Return 0 for failure
-        return Ok(Bytes::new());
+    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+        U256::from(23800)
+    }
+}
+pub struct UnimplementedPrecompile;
+impl Precompile for UnimplementedPrecompile {
+    fn execute(&self, _evm: &mut Machine, _context: &Context) -> Result<Option<Bytes>, EvmError> {
+        Err(EvmError::ExecutionFailed)
     }
 
-    // Commit checkpoint on success
-    let _ = worldstate.commit();
-    
-    // Update gas remaining
-    evm.gas_remaining = callee_evm.gas_remaining;
-    
-    // Push success (1) to stack
-    evm.stack_push(U256::from(1))?;
-    Ok(Bytes::new())
+    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+        U256::zero()
+    }
 }
 
-fn op_create(evm: &mut Machine, context: &Context, worldstate: &mut WorldStateTrie, _substate: &mut Substate) -> ExecuteResult {
-    let value = evm.stack_pop()?;
-    let offset = evm.stack_pop()?;
-    let size = evm.stack_pop()?;
+/// precompiled contracts by address, used both to dispatch calls and as the initial EIP-2929 warm set.
+/// the registry of each fork is built once and shared; embedders can clone one and register
+/// their own implementations under custom addresses
+#[derive(Clone, Default)]
+pub struct Precompiles {
+    contracts: HashMap<Address, Arc<dyn Precompile>>,
+}
 
-    let caller = context.contract_addr.unwrap_or(context.sender);
+impl Precompiles {
+    pub fn berlin() -> &'static Precompiles {
+        &BERLIN_PRECOMPILES
+    }
 
-    if evm.call_depth >= 1024 {
-        return Err(EvmError::CallDepthExceeded);
+    pub fn cancun() -> &'static Precompiles {
+        &CANCUN_PRECOMPILES
     }
 
-    if value > worldstate.get_balance(&caller).unwrap_or(U256::zero()) {
-        return Err(EvmError::InsufficientBalance);
+    pub fn prague() -> &'static Precompiles {
+        &PRAGUE_PRECOMPILES
     }
 
-    // Get init code from memory
-    let offset_usize = offset.as_usize();
-    let size_usize = size.as_usize();
-    if offset_usize + size_usize > evm.memory.len() {
-        return Err(EvmError::MemoryOutOfBounds);
+    pub fn for_spec(spec: SpecId) -> &'static Precompiles {
+        if spec.is_enabled_in(SpecId::Prague) {
+            Self::prague()
+        } else if spec.is_enabled_in(SpecId::Cancun) {
+            Self::cancun()
+        } else {
+            Self::berlin()
+        }
     }
-    let init_code = evm.memory.slice(offset_usize..offset_usize + size_usize).to_vec();
 
-    // Calculate contract address (simplified: use nonce)
-    let nonce = worldstate.get_nonce(&caller).unwrap_or(0);
-    let mut address_bytes = [0u8; 20];
-    let mut hasher = Keccak256::new();
-    hasher.update(caller.as_bytes());
-    hasher.update(&nonce.to_be_bytes());
-    let hash = hasher.finalize();
-    address_bytes.copy_from_slice(&hash[12..]);
-    let contract_addr = Address::from_slice(&address_bytes);
-
-    // Create account
-    let account = AccountState::new(&init_code);
-    worldstate.insert(&contract_addr, account);
-    worldstate.set_nonce(&caller, nonce + 1);
+    /// add a precompile, replacing the one already registered at the address if any
+    pub fn register(&mut self, address: Address, precompile: impl Precompile + 'static) {
+        self.contracts.insert(address, Arc::new(precompile));
+    }
 
-    // Transfer value
-    if value > U256::zero() {
-        let caller_balance = worldstate.get_balance(&caller).unwrap_or(U256::zero());
-        let contract_balance = worldstate.get_balance(&contract_addr).unwrap_or(U256::zero());
-        worldstate.set_balance(&caller, caller_balance - value);
-        worldstate.set_balance(&contract_addr, contract_balance + value);
+    pub fn get(&self, address: &Address) -> Option<&dyn Precompile> {
+        self.contracts.get(address).map(|precompile| precompile.as_ref())
     }
 
-    // Execute init code (simplified: just set the code)
-    // In real EVM, init code execution would return the contract code
-    // For simplicity, we use the init code as the contract code
-    worldstate.set_code(&contract_addr, init_code);
+    pub fn contains(&self, address: &Address) -> bool {
+        self.contracts.contains_key(address)
+    }
 
-    // Push contract address to stack
-    let addr_u256 = U256::from_big_endian(contract_addr.as_bytes());
-    evm.stack_push(addr_u256)?;
+    pub fn addresses(&self) -> impl Iterator<Item = Address> + '_ {
+        self.contracts.keys().copied()
+    }
 
-    Ok(Bytes::new())
+    /// add every precompile of `other`, replacing the ones already registered at the same addresses
+    pub fn extend(&mut self, other: &Precompiles) {
+        self.contracts.extend(other.contracts.iter().map(|(address, precompile)| (*address, precompile.clone())));
+    }
 }
 
-pub static JUMP_TABLE: Lazy<HashMap<u8, Operation>> = Lazy::new(|| {
-    let mut table = HashMap::new();
-    let push_ops: [ExecutionFunc; 32] = [
-        op_push1, op_push2, op_push3, op_push4, op_push5, op_push6, op_push7, op_push8,
-        op_push9, op_push10, op_push11, op_push12, op_push13, op_push14, op_push15, op_push16,
-        op_push17, op_push18, op_push19, op_push20, op_push21, op_push22, op_push23, op_push24,
-        op_push25, op_push26, op_push27, op_push28, op_push29, op_push30, op_push31, op_push32,
-    ];
-
-    // STOP
-    table.insert(
-        opcodes::STOP,
-        Operation::new(
-            opcodes::STOP,
-            op_stop,
-            0,     // constant gas (STOP has 0 gas cost)
-            None,  // no dynamic gas
-            0,     // min stack (no stack operations)
-            1024,  // max stack
-            None,  // no memory size
-        ),
-    );
-
-    // ADD
-    table.insert(
-        0x01,
-        Operation::new(
-            opcodes::ADD,
-            op_add,
-            3,     // constant gas
-            None,  // no dynamic gas
-            2,     // min stack
-            1024,  // max stack
-            None,  // no memory size
-        ),
-    );
+static BERLIN_PRECOMPILES: Lazy<Precompiles> = Lazy::new(|| {
+    let mut precompiles = Precompiles::default();
+    precompiles.register(Address::from_low_u64_be(0x01), Ecrecover);
+    precompiles.register(Address::from_low_u64_be(0x02), Sha256Hash);
+    precompiles.register(Address::from_low_u64_be(0x03), Ripemd160Hash);
+    precompiles.register(Address::from_low_u64_be(0x04), DataCopy);
+    precompiles.register(Address::from_low_u64_be(0x05), ModExp);
+    precompiles.register(Address::from_low_u64_be(0x06), EcAdd);
+    precompiles.register(Address::from_low_u64_be(0x07), EcMul);
+    precompiles.register(Address::from_low_u64_be(0x08), EcPairing);
+    precompiles.register(Address::from_low_u64_be(0x09), Blake2F);
+    precompiles
+});
 
-    // CALLDATALOAD
-    table.insert(
-        opcodes::CALLDATALOAD,
-        Operation::new(
-            opcodes::CALLDATALOAD,
-            op_calldataload,
-            3,
-            None,
-            1,
-            1024,
-            None,
-        ),
-    );
+// EIP-4844 adds the point evaluation precompile in Cancun
+static CANCUN_PRECOMPILES: Lazy<Precompiles> = Lazy::new(|| {
+    let mut precompiles = BERLIN_PRECOMPILES.clone();
+    precompiles.register(Address::from_low_u64_be(0x0A), PointEvaluation);
+    precompiles
+});
 
-    // SSTORE
-    table.insert(
-        opcodes::SSTORE,
-        Operation::new(
-            opcodes::SSTORE,
-            op_sstore,
-            0,
-            None,
-            2,
-            1024,
-            None,
-        ),
-    );
+// EIP-2537 adds the BLS12-381 precompiles in Prague
+static PRAGUE_PRECOMPILES: Lazy<Precompiles> = Lazy::new(|| {
+    let mut precompiles = CANCUN_PRECOMPILES.clone();
+    precompiles.register(Address::from_low_u64_be(0x0B), Bls12G1Add);
+    precompiles.register(Address::from_low_u64_be(0x0C), Bls12G1Msm);
+    precompiles.register(Address::from_low_u64_be(0x0D), Bls12G2Add);
+    precompiles.register(Address::from_low_u64_be(0x0E), Bls12G2Msm);
+    precompiles.register(Address::from_low_u64_be(0x0F), Bls12Pairing);
+    precompiles.register(Address::from_low_u64_be(0x10), Bls12MapFpToG1);
+    precompiles.register(Address::from_low_u64_be(0x11), Bls12MapFp2ToG2);
+    precompiles
+});
 
-    // PUSH1..PUSH32
-    for i in 0..32u8 {
-        let opcode = opcodes::PUSH1 + i;
-        table.insert(
-            opcode,
-            Operation::new(
-                opcode,
-                push_ops[i as usize],
-                3,
-                None,
-                0,
-                1024,
-                None,
-            ),
-        );
-    }
 
-    // CALL
-    table.insert(
-        opcodes::CALL,
-        Operation::new(
-            opcodes::CALL,
-            op_call,
-            0,     // dynamic gas is charged in op_call
-            None,  // dynamic gas calculated in operation
-            7,     // min stack (gas, addr, value, in_offset, in_size, out_offset, out_size)
-            1024,  // max stack
-            None,  // memory size calculated in operation
-        ),
-    );
 
-    // CREATE
-    table.insert(
-        opcodes::CREATE,
-        Operation::new(
-            opcodes::CREATE,
-            op_create,
-            32000, // base gas cost
-            None,  // dynamic gas calculated in operation
-            3,     // min stack (value, offset, size)
-            1024,  // max stack
-            None,  // memory size calculated in operation
-        ),
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    table
-});
+    #[test]
+    fn test_analyze_jumpdests_skips_push_data() {
+        // PUSH2 0x5b5b, JUMPDEST, PUSH1 0x5b, JUMPDEST, PUSH32 truncated by the end of code: 0x5b
+        let code = [0x61, 0x5b, 0x5b, 0x5b, 0x60, 0x5b, 0x5b, 0x7f, 0x5b];
+        let bitmap = analyze_jumpdests(&code);
+        let valid: Vec<usize> = (0..code.len() + 8).filter(|&i| bitmap.contains(i)).collect();
+        assert_eq!(valid, vec![3, 6]);
+    }
 
-/* -------------------------------------------------------------------------- */
-/*                                 precompile                                 */
-/* -------------------------------------------------------------------------- */
-pub trait Precompile {
-    fn execute(&self, evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError>;
-    fn gas_cost(&self, evm: &Machine, context: &Context) -> U256;
-}
+    // a frame at the start of a transaction in `spec`, tests override the fields they need
+    fn test_context<'a>(spec: SpecId) -> (Machine, Context<'a>, Substate, WorldStateTrie) {
+        let block: &'static crate::block::Block = Box::leak(Box::default());
+        let precompiles = Precompiles::for_spec(spec);
+        let evm = Machine {
+            memory: vec![],
+            stack: vec![],
+            pc: 0,
+            gas_remaining: U256::from(1_000_000),
+            call_depth: 0,
+            return_data: Bytes::new(),
+        };
+        let context = Context {
+            contract_addr: None,
+            origin_sender: Address::zero(),
+            gas_price: U256::zero(),
+            input: Bytes::new(),
+            sender: Address::zero(),
+            value: U256::zero(),
+            code: vec![],
+            code_hash: H256::zero(),
+            block,
+            ancestors: &[],
+            blob_hashes: vec![],
+            spec,
+            precompiles,
+            depth: 0,
+            allow_writes: true,
+        };
+        let substate = Substate {
+            self_destruct: vec![],
+            created_accounts: vec![],
+            logs: vec![],
+            touched_accounts: vec![],
+            refund_fee: U256::zero(),
+            access_list_accounts: precompiles.addresses().collect(),
+            access_list_storage: vec![],
+            preimages: None,
+        };
+        (evm, context, substate, WorldStateTrie::new())
+    }
 
+    #[test]
+    fn test_blockhash_reads_recent_ancestors() {
+        use crate::block::Block;
+        // the hash of block i is i + 0x1000 here, recorded in the parent hash of block i + 1
+        let block_at = |number: u64| {
+            let mut block = Block::default();
+            block.header.number = number;
+            block.header.parent_hash = H256::from_low_u64_be(number - 1 + 0x1000);
+            block
+        };
+        let ancestors: Vec<Block> = (1..300).map(block_at).collect();
+        let current = block_at(300);
+        let (_, mut context, mut substate, mut worldstate) = test_context(SpecId::LATEST);
+        context.block = &current;
+        context.ancestors = &ancestors;
+        let mut blockhash = |number: u64| {
+            let (mut evm, ..) = test_context(SpecId::LATEST);
+            evm.stack.push(U256::from(number));
+            op_blockhash(&mut evm, &context, &mut worldstate, &mut substate).unwrap();
+            evm.stack_pop().unwrap()
+        };
+        assert_eq!(blockhash(299), U256::from(299 + 0x1000));
+        assert_eq!(blockhash(150), U256::from(150 + 0x1000));
+        assert_eq!(blockhash(44), U256::from(44 + 0x1000));
+        assert_eq!(blockhash(43), U256::zero()); // older than 256 blocks
+        assert_eq!(blockhash(300), U256::zero());
+    }
 
-pub struct Ecrecover;
-impl Precompile for Ecrecover {
-    fn execute(&self, _evm: &mut Machine, _context: &Context) -> Result<Option<Bytes>, EvmError> {
-        Ok(Some(Bytes::from(vec![0u8; 32])))
+    #[test]
+    fn test_create2_gas_with_huge_init_code_is_out_of_gas() {
+        // before Shanghai there is no init code limit, the size must not overflow usize
+        let (mut evm, context, ..) = test_context(SpecId::Berlin);
+        evm.stack = vec![U256::zero(), U256::MAX, U256::zero(), U256::zero()];
+        assert!(matches!(gas_create2(&evm, &context), Err(EvmError::OutOfGas)));
     }
 
-    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
-        U256::from(3000)
+    #[test]
+    fn test_keccak256_records_preimages_once_enabled() {
+        let (_, context, mut substate, _) = test_context(SpecId::LATEST);
+        // a mapping slot: keccak256(key . position) with key 0x2a at position 0
+        let mut input = [0u8; 64];
+        input[31] = 0x2a;
+        let keccak = |substate: &mut Substate| {
+            let (mut evm, .., mut worldstate) = test_context(SpecId::LATEST);
+            evm.memory = input.to_vec();
+            evm.stack = vec![U256::from(64), U256::zero()];
+            op_keccak256(&mut evm, &context, &mut worldstate, substate).unwrap();
+            H256::from(evm.stack_pop().unwrap().to_big_endian())
+        };
+
+        let hash = keccak(&mut substate);
+        assert_eq!(hash, H256::from_slice(&Keccak256::digest(input)));
+        assert_eq!(substate.preimages, None);
+
+        substate.preimages = Some(vec![]);
+        keccak(&mut substate);
+        assert_eq!(substate.preimages, Some(vec![(hash, input.to_vec())]));
+
+        // a reverted frame takes its preimages with it
+        let checkpoint = substate.checkpoint();
+        keccak(&mut substate);
+        substate.rollback(checkpoint);
+        assert_eq!(substate.preimages, Some(vec![(hash, input.to_vec())]));
     }
-}
-pub struct Sha256Hash;
-impl Precompile for Sha256Hash {
-    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
-        use sha3::{Digest, Sha3_256};
-        let mut hasher = Sha3_256::new();
-        hasher.update(&context.input);
-        let hash = hasher.finalize();
-        Ok(Some(Bytes::copy_from_slice(&hash)))
+
+    // the output and the gas used by a precompile call with 1,000,000 gas
+    fn try_run_precompile_on(precompile: &dyn Precompile, input: &[u8]) -> (ExecuteResult, U256) {
+        let (mut evm, mut context, ..) = test_context(SpecId::LATEST);
+        context.input = Bytes::copy_from_slice(input);
+        let result = run_precompile(precompile, &mut evm, &context);
+        (result, U256::from(1_000_000) - evm.gas_remaining)
     }
 
-    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
-        let l = context.input.len();
-        U256::from(60 + ((l + 31) / 32) * 12) // basegas:60, gas per word: 12
+    fn run_precompile_on(precompile: &dyn Precompile, input: &[u8]) -> Bytes {
+        try_run_precompile_on(precompile, input).0.unwrap()
     }
-}
-pub struct DataCopy;
-impl Precompile for DataCopy {
-    fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
-        Ok(Some(context.input.clone()))
+
+    #[test]
+    fn test_sha256_and_ripemd160_known_vectors() {
+        use hex_literal::hex;
+        assert_eq!(
+            run_precompile_on(&Sha256Hash, b"")[..],
+            hex!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        assert_eq!(
+            run_precompile_on(&Sha256Hash, b"abc")[..],
+            hex!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(
+            run_precompile_on(&Ripemd160Hash, b"")[..],
+            hex!("0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31")
+        );
+        assert_eq!(
+            run_precompile_on(&Ripemd160Hash, b"abc")[..],
+            hex!("0000000000000000000000008eb208f7e05d987a9b044a8e98c6b087f15a0bfc")
+        );
     }
 
-    fn gas_cost(&self, _evm: &Machine, context: &Context) -> U256 {
-        let l = context.input.len();
-        U256::from(15 + ((l + 31) / 32) * 3) // basegas:15, gas per word: 3
+    // the EIP-2537 test vectors; the mul_* files predate the separate MUL precompiles being dropped
+    // and are run as single pair MSMs
+    #[test]
+    fn test_bls12_381_precompile_vectors() {
+        let suites: [(&str, &dyn Precompile); 9] = [
+            ("add_G1_bls", &Bls12G1Add),
+            ("mul_G1_bls", &Bls12G1Msm),
+            ("multiexp_G1_bls", &Bls12G1Msm),
+            ("add_G2_bls", &Bls12G2Add),
+            ("mul_G2_bls", &Bls12G2Msm),
+            ("multiexp_G2_bls", &Bls12G2Msm),
+            ("pairing_check_bls", &Bls12Pairing),
+            ("map_fp_to_G1_bls", &Bls12MapFpToG1),
+            ("map_fp2_to_G2_bls", &Bls12MapFp2ToG2),
+        ];
+        let load = |file: String| -> Vec<serde_json::Value> {
+            let path = format!("{}/tests/data/bls12_381/{file}.json", env!("CARGO_MANIFEST_DIR"));
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+        };
+        let input_of = |case: &serde_json::Value| hex::decode(case["Input"].as_str().unwrap()).unwrap();
+
+        for (suite, precompile) in suites {
+            for case in load(suite.to_string()) {
+                let expected = hex::decode(case["Expected"].as_str().unwrap()).unwrap();
+                let (result, _) = try_run_precompile_on(precompile, &input_of(&case));
+                assert_eq!(result.ok().map(|output| output.to_vec()), Some(expected), "{}", case["Name"]);
+            }
+            for case in load(format!("fail-{suite}")) {
+                let (result, gas_used) = try_run_precompile_on(precompile, &input_of(&case));
+                assert!(result.is_err(), "{}", case["Name"]);
+                assert_eq!(gas_used, U256::from(1_000_000), "{}", case["Name"]);
+            }
+        }
     }
-}
-pub struct UnimplementedPrecompile;
-impl Precompile for UnimplementedPrecompile {
-    fn execute(&self, _evm: &mut Machine, _context: &Context) -> Result<Option<Bytes>, EvmError> {
-        Err(EvmError::ExecutionFailed)
+
+    #[test]
+    fn test_bls12_381_gas_schedule() {
+        let gas_used = |precompile: &dyn Precompile, input_length: usize| {
+            try_run_precompile_on(precompile, &vec![0u8; input_length]).1
+        };
+        assert_eq!(gas_used(&Bls12G1Add, 256), U256::from(375));
+        assert_eq!(gas_used(&Bls12G2Add, 512), U256::from(600));
+        // all-zero inputs are points at infinity
+        assert_eq!(gas_used(&Bls12G1Msm, 160), U256::from(12000));
+        assert_eq!(gas_used(&Bls12G1Msm, 2 * 160), U256::from(2 * 12000 * 949 / 1000));
+        assert_eq!(gas_used(&Bls12G2Msm, 2 * 288), U256::from(2 * 22500));
+        assert_eq!(gas_used(&Bls12G2Msm, 3 * 288), U256::from(3 * 22500 * 923 / 1000));
+        assert_eq!(gas_used(&Bls12Pairing, 2 * 384), U256::from(37700 + 2 * 32600));
+        assert_eq!(gas_used(&Bls12MapFpToG1, 64), U256::from(5500));
+        assert_eq!(gas_used(&Bls12MapFp2ToG2, 128), U256::from(23800));
+
+        // the discount stops decreasing after 128 pairs
+        assert_eq!(bls_msm_gas(128, 12000, &BLS12_381_G1_MSM_DISCOUNT), U256::from(128 * 12000 * 519 / 1000));
+        assert_eq!(bls_msm_gas(200, 12000, &BLS12_381_G1_MSM_DISCOUNT), U256::from(200 * 12000 * 519 / 1000));
+        assert_eq!(bls_msm_gas(200, 22500, &BLS12_381_G2_MSM_DISCOUNT), U256::from(200u64 * 22500 * 524 / 1000));
     }
 
-    fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
-        U256::zero()
+    #[test]
+    fn test_point_evaluation_without_trusted_setup_is_fatal() {
+        // the unit tests never load the trusted setup, the input is well formed up to the proof check
+        let commitment = [0u8; 48];
+        let mut input = crypto::kzg_to_versioned_hash(&commitment).as_bytes().to_vec();
+        input.extend_from_slice(&[0u8; 64]);
+        input.extend_from_slice(&commitment);
+        input.extend_from_slice(&[0u8; 48]);
+        let (result, _) = try_run_precompile_on(&PointEvaluation, &input);
+        assert!(matches!(result, Err(EvmError::Fatal(_))));
+        assert!(crypto::load_kzg_trusted_setup(std::path::Path::new("no_such_trusted_setup.txt")).is_err());
     }
-}
 
-pub type PrecompiledContracts = HashMap<Address, Box<dyn Precompile>>;
+    #[test]
+    fn test_precompile_registries_per_fork() {
+        let has = |precompiles: &Precompiles, address: u64| precompiles.contains(&Address::from_low_u64_be(address));
+        assert_eq!(Precompiles::berlin().addresses().count(), 9);
+        assert!(has(Precompiles::berlin(), 0x09) && !has(Precompiles::berlin(), 0x0A));
+        assert_eq!(Precompiles::cancun().addresses().count(), 10);
+        assert!(has(Precompiles::cancun(), 0x0A) && !has(Precompiles::cancun(), 0x0B));
+        assert_eq!(Precompiles::prague().addresses().count(), 17);
+        assert!(has(Precompiles::prague(), 0x11) && !has(Precompiles::prague(), 0x12));
+    }
 
-pub fn precompiled_contracts_berlin() -> PrecompiledContracts {
-    let mut contracts: PrecompiledContracts = HashMap::new();
-    contracts.insert(Address::from_low_u64_be(0x01), Box::new(Ecrecover));
-    contracts.insert(Address::from_low_u64_be(0x02), Box::new(Sha256Hash));
-    contracts.insert(Address::from_low_u64_be(0x03), Box::new(UnimplementedPrecompile));
-    contracts.insert(Address::from_low_u64_be(0x04), Box::new(DataCopy));
-    contracts.insert(Address::from_low_u64_be(0x05), Box::new(UnimplementedPrecompile));
-    contracts.insert(Address::from_low_u64_be(0x06), Box::new(UnimplementedPrecompile));
-    contracts.insert(Address::from_low_u64_be(0x07), Box::new(UnimplementedPrecompile));
-    contracts.insert(Address::from_low_u64_be(0x08), Box::new(UnimplementedPrecompile));
-    contracts.insert(Address::from_low_u64_be(0x09), Box::new(UnimplementedPrecompile));
-    contracts
-}
+    #[test]
+    fn test_custom_precompile_is_dispatched_and_warm() {
+        struct Echo;
+        impl Precompile for Echo {
+            fn execute(&self, _evm: &mut Machine, context: &Context) -> Result<Option<Bytes>, EvmError> {
+                Ok(Some(context.input.clone()))
+            }
 
-fn is_precompile(address: &Address) -> bool {
-    let precompiled_contracts = precompiled_contracts_berlin();
-    precompiled_contracts.contains_key(address)
+            fn gas_cost(&self, _evm: &Machine, _context: &Context) -> U256 {
+                U256::from(10)
+            }
+        }
+        let echo = Address::from_low_u64_be(0xbeef);
+        let mut precompiles = Precompiles::prague().clone();
+        precompiles.register(echo, Echo);
+        assert!(!Precompiles::prague().contains(&echo));
+
+        let (mut evm, mut context, mut substate, mut worldstate) = test_context(SpecId::LATEST);
+        context.precompiles = &precompiles;
+        substate.access_list_accounts = precompiles.addresses().collect();
+        // STATICCALL(gas, echo, 0, 32, 32, 32) with memory[0..32] as input
+        let input = [0x2a; 32];
+        evm.memory = [input, [0; 32]].concat();
+        evm.stack = vec![U256::from(32), U256::from(32), U256::from(32), U256::zero(), U256::from(0xbeef), U256::from(1000)];
+        evm.gas_remaining = U256::from(100_000);
+        op_staticcall(&mut evm, &context, &mut worldstate, &mut substate).unwrap();
+        assert_eq!(evm.stack_pop().unwrap(), U256::one());
+        assert_eq!(evm.memory[32..], input);
+        // warm from the start: G_warmaccess and the precompile gas
+        assert_eq!(evm.gas_remaining, U256::from(100_000 - 100 - 10));
+    }
 }
-
@@ -0,0 +1,156 @@
+use std::str::FromStr;
+
+/// The Ethereum hardforks the executor implements, in activation order. The fork selects the
+/// instruction set, gas schedule, refund rules, precompiles and validation rules used to execute
+/// a transaction or a block. Forks before Berlin are not implemented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpecId {
+    Berlin,         // EIP-2929 access costs, EIP-2930 access lists
+    London,         // EIP-1559 base fee, EIP-3529 refunds, EIP-3541
+    Paris,          // the merge: PREVRANDAO replaces DIFFICULTY
+    Shanghai,       // EIP-3651 warm coinbase, EIP-3855 PUSH0, EIP-3860 init code limit
+    Cancun,         // EIP-1153, EIP-4844, EIP-5656, EIP-6780, EIP-7516
+    Prague,         // EIP-2537 BLS12-381 precompiles, EIP-7623 calldata floor, EIP-7691
+}
+
+impl SpecId {
+    pub const LATEST: SpecId = SpecId::Prague;
+
+    /// whether the rules of `fork` apply, i.e. `fork` is this spec or an earlier one
+    pub fn is_enabled_in(self, fork: SpecId) -> bool {
+        self >= fork
+    }
+
+    /// the name used for the fork in the post section of the Ethereum state tests
+    pub fn name(self) -> &'static str {
+        match self {
+            SpecId::Berlin => "Berlin",
+            SpecId::London => "London",
+            SpecId::Paris => "Paris",
+            SpecId::Shanghai => "Shanghai",
+            SpecId::Cancun => "Cancun",
+            SpecId::Prague => "Prague",
+        }
+    }
+}
+
+impl FromStr for SpecId {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let spec = match name {
+            "Berlin" => SpecId::Berlin,
+            "London" => SpecId::London,
+            "Paris" | "Merge" => SpecId::Paris,
+            "Shanghai" => SpecId::Shanghai,
+            "Cancun" => SpecId::Cancun,
+            "Prague" => SpecId::Prague,
+            _ => return Err(anyhow::anyhow!("unknown or unsupported fork {}", name)),
+        };
+        Ok(spec)
+    }
+}
+
+impl std::fmt::Display for SpecId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// what activates a fork: a block number before the merge, a block timestamp from Shanghai
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForkActivation {
+    Block(u64),
+    Timestamp(u64),
+}
+
+impl ForkActivation {
+    fn is_active(self, number: u64, timestamp: u64) -> bool {
+        match self {
+            ForkActivation::Block(block) => number >= block,
+            ForkActivation::Timestamp(time) => timestamp >= time,
+        }
+    }
+}
+
+/// the forks of a chain and when each of them activates, a block follows the latest active one
+#[derive(Debug, Clone)]
+pub struct ForkSchedule {
+    forks: Vec<(SpecId, ForkActivation)>,
+}
+
+impl ForkSchedule {
+    /// a chain that follows `spec` from genesis on
+    pub fn fixed(spec: SpecId) -> Self {
+        Self { forks: vec![(spec, ForkActivation::Block(0))] }
+    }
+
+    /// the Ethereum mainnet activations of the supported forks, blocks before Berlin cannot be executed
+    pub fn mainnet() -> Self {
+        Self { forks: vec![(SpecId::Berlin, ForkActivation::Block(12_244_000))] }
+            .with_fork(SpecId::London, ForkActivation::Block(12_965_000))
+            .with_fork(SpecId::Paris, ForkActivation::Block(15_537_394))
+            .with_fork(SpecId::Shanghai, ForkActivation::Timestamp(1_681_338_455))
+            .with_fork(SpecId::Cancun, ForkActivation::Timestamp(1_710_338_135))
+            .with_fork(SpecId::Prague, ForkActivation::Timestamp(1_746_612_311))
+    }
+
+    pub fn with_fork(mut self, spec: SpecId, activation: ForkActivation) -> Self {
+        self.forks.push((spec, activation));
+        self
+    }
+
+    /// whether some block of the chain follows the rules of `spec`
+    pub fn reaches(&self, spec: SpecId) -> bool {
+        self.forks.iter().any(|(fork, _)| fork.is_enabled_in(spec))
+    }
+
+    /// the fork of the block with this number and timestamp, an error before any fork activates
+    pub fn spec_at(&self, number: u64, timestamp: u64) -> anyhow::Result<SpecId> {
+        self.forks.iter()
+            .filter(|(_, activation)| activation.is_active(number, timestamp))
+            .map(|(spec, _)| *spec)
+            .max()
+            .ok_or_else(|| anyhow::anyhow!("no supported fork is active at block {} (timestamp {})", number, timestamp))
+    }
+}
+
+impl From<SpecId> for ForkSchedule {
+    fn from(spec: SpecId) -> Self {
+        Self::fixed(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fork_names_round_trip_in_activation_order() {
+        let forks = ["Berlin", "London", "Paris", "Shanghai", "Cancun", "Prague"];
+        let specs: Vec<SpecId> = forks.iter().map(|name| name.parse().unwrap()).collect();
+        assert!(specs.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(specs.iter().map(|spec| spec.name()).collect::<Vec<_>>(), forks);
+        assert_eq!("Merge".parse::<SpecId>().unwrap(), SpecId::Paris);
+        assert!("Osaka".parse::<SpecId>().is_err());
+        assert!("Istanbul".parse::<SpecId>().is_err());
+    }
+
+    #[test]
+    fn test_fork_schedule_follows_the_latest_active_fork() {
+        let schedule = ForkSchedule::fixed(SpecId::Berlin)
+            .with_fork(SpecId::London, ForkActivation::Block(5))
+            .with_fork(SpecId::Shanghai, ForkActivation::Timestamp(1000));
+        assert_eq!(schedule.spec_at(0, 0).unwrap(), SpecId::Berlin);
+        assert_eq!(schedule.spec_at(4, 999).unwrap(), SpecId::Berlin);
+        assert_eq!(schedule.spec_at(5, 999).unwrap(), SpecId::London);
+        assert_eq!(schedule.spec_at(6, 1000).unwrap(), SpecId::Shanghai);
+        assert_eq!(ForkSchedule::from(SpecId::Cancun).spec_at(100, 100).unwrap(), SpecId::Cancun);
+
+        let mainnet = ForkSchedule::mainnet();
+        assert!(mainnet.spec_at(12_243_999, 1_618_481_214).is_err());
+        assert_eq!(mainnet.spec_at(12_244_000, 1_618_481_223).unwrap(), SpecId::Berlin);
+        assert_eq!(mainnet.spec_at(12_965_000, 1_628_166_822).unwrap(), SpecId::London);
+        assert_eq!(mainnet.spec_at(19_426_587, 1_710_338_135).unwrap(), SpecId::Cancun);
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::vec;
 
 /// implemented a run framework for the vm. Support ADD, CALL, CREATE, STOP
@@ -8,7 +9,8 @@ use bytes::Bytes;
 
 use crate::world_state::{WorldStateTrie, AccountState};
 use crate::block::Block;
-use crate::operations::{JUMP_TABLE, opcodes};
+use crate::operations::{jump_table, run_precompile, Operation, opcodes, Precompiles};
+use crate::spec::SpecId;
 use crate::receipts::{Log, Receipt};
 use rlp::RlpStream;
 use sha3::{Digest, Keccak256};
@@ -23,7 +25,12 @@ pub enum EvmError {
     CallDepthExceeded,
     InsufficientBalance,
     MemoryOutOfBounds,
+    InvalidJump,
+    ReturnDataOutOfBounds,
+    InitCodeSizeExceeded,
+    WriteProtection, // state modification in a static context
     ExecutionFailed,
+    Fatal(String), // the executor cannot go on, e.g. a precompile setup is missing; aborts the transaction
     // Many other errors
 
     // special operations, not the actual errors
@@ -43,7 +50,12 @@ impl std::fmt::Display for EvmError {
             EvmError::CallDepthExceeded => write!(f, "Call depth exceeded"),
             EvmError::InsufficientBalance => write!(f, "Insufficient balance"),
             EvmError::MemoryOutOfBounds => write!(f, "Memory out of bounds"),
+            EvmError::InvalidJump => write!(f, "Invalid jump destination"),
+            EvmError::ReturnDataOutOfBounds => write!(f, "Return data out of bounds"),
+            EvmError::InitCodeSizeExceeded => write!(f, "Init code size exceeded"),
+            EvmError::WriteProtection => write!(f, "Write protection"),
             EvmError::ExecutionFailed => write!(f, "Execution failed"),
+            EvmError::Fatal(reason) => write!(f, "Fatal: {}", reason),
             EvmError::ExplicitStop => write!(f, "Explicit stop"),
             EvmError::Return(_) => write!(f, "Return"),
             EvmError::Revert(_) => write!(f, "Revert"),
@@ -55,15 +67,17 @@ impl std::fmt::Display for EvmError {
 impl std::error::Error for EvmError {}
 
 pub type ExecuteResult = Result<Bytes, EvmError>;
-const G_CODE_DEPOSIT: u64 = 200;
-const MAX_CODE_SIZE: usize = 24_576;
+pub(crate) const G_CODE_DEPOSIT: u64 = 200;
+pub(crate) const MAX_CODE_SIZE: usize = 24_576;
+pub(crate) const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE; // EIP-3860
 
 pub struct Machine {
-    pub memory: Bytes, 
+    pub memory: Vec<u8>, // word-aligned, grows on demand
     pub stack: Vec<U256>,
     pub pc: usize,
     pub gas_remaining: U256,
     pub call_depth: u64,
+    pub return_data: Bytes, // output of the last sub-call, EIP-211
 }
 
 pub struct Context<'a> {
@@ -74,42 +88,131 @@ pub struct Context<'a> {
     pub sender: Address,
     pub value: U256,
     pub code: Vec<u8>,
+    pub code_hash: H256, // key of the cached JUMPDEST analysis
     pub block: &'a Block,
+    pub ancestors: &'a [Block], // earlier blocks of the chain, for BLOCKHASH
+    pub blob_hashes: Vec<H256>, // versioned hashes of a blob transaction, for BLOBHASH
+    pub spec: SpecId,
+    pub precompiles: &'a Precompiles,
     pub depth: u64,
     pub allow_writes: bool,
 }
 
 pub struct Substate {
     pub self_destruct: Vec<Address>,
+    pub created_accounts: Vec<Address>, // EIP-6780: only these can be deleted by SELFDESTRUCT
     pub logs: Vec<Log>,
     pub touched_accounts: Vec<Address>,
     pub refund_fee: U256,
     pub access_list_accounts: Vec<Address>,
     pub access_list_storage: Vec<(Address, H256)>, // (address, storage_key)
+    /// KECCAK256 的 (哈希, 原像)，为 None 时不记录；随所在帧一起回滚，只在一笔交易内有效
+    pub preimages: Option<Vec<(H256, Vec<u8>)>>,
+}
+
+/// sizes of the substate lists before a sub-call, to discard what a failed call added
+pub struct SubstateCheckpoint {
+    self_destruct: usize,
+    created_accounts: usize,
+    logs: usize,
+    touched_accounts: usize,
+    refund_fee: U256,
+    access_list_accounts: usize,
+    access_list_storage: usize,
+    preimages: usize,
+}
+
+impl Substate {
+    pub fn checkpoint(&self) -> SubstateCheckpoint {
+        SubstateCheckpoint {
+            self_destruct: self.self_destruct.len(),
+            created_accounts: self.created_accounts.len(),
+            logs: self.logs.len(),
+            touched_accounts: self.touched_accounts.len(),
+            refund_fee: self.refund_fee,
+            access_list_accounts: self.access_list_accounts.len(),
+            access_list_storage: self.access_list_storage.len(),
+            preimages: self.preimages.as_ref().map_or(0, Vec::len),
+        }
+    }
+
+    /// a reverted frame also reverts its logs, refunds and EIP-2929 accesses
+    pub fn rollback(&mut self, checkpoint: SubstateCheckpoint) {
+        self.self_destruct.truncate(checkpoint.self_destruct);
+        self.created_accounts.truncate(checkpoint.created_accounts);
+        self.logs.truncate(checkpoint.logs);
+        self.touched_accounts.truncate(checkpoint.touched_accounts);
+        self.refund_fee = checkpoint.refund_fee;
+        self.access_list_accounts.truncate(checkpoint.access_list_accounts);
+        self.access_list_storage.truncate(checkpoint.access_list_storage);
+        if let Some(preimages) = self.preimages.as_mut() {
+            preimages.truncate(checkpoint.preimages);
+        }
+    }
 }
 
 fn settle_failed_execution(
     tx: &Transaction1or2,
     state: &mut WorldStateTrie,
     block: &mut Block,
+    spec: SpecId,
     gas_remaining: U256,
 ) -> Result<(), anyhow::Error> {
     let _ = state.rollback();
+    state.clear_transient_storage();
+
+    // gas left by a REVERT goes back to the sender, the gas used still pays the beneficiary
+    let base_fee = block_base_fee(block, spec);
+    let gas_remaining = apply_calldata_floor(tx, spec, gas_remaining);
+    let sender = tx.get_sender()?;
+    let sender_bal = state.get_balance(&sender).unwrap_or(U256::zero());
+    state.set_balance(&sender, sender_bal + gas_remaining * tx.effective_gas_price(base_fee));
+    let gas_used = U256::from(tx.gas_limit) - gas_remaining;
+    pay_beneficiary(state, block, gas_used * tx.priority_fee_per_gas(base_fee));
+
     let receipt = Receipt::new(
         tx.tx_type,
         0, // 0 for failure
-        U256::from(tx.gas_limit) - gas_remaining,
+        gas_used,
         vec![], // failure has no logs
     );
     block.receipts.push(receipt);
     Ok(())
 }
 
+fn pay_beneficiary(state: &mut WorldStateTrie, block: &Block, reward: U256) {
+    if reward.is_zero() {
+        return;
+    }
+    if state.get_account(&block.header.beneficiary).is_none() {
+        state.insert(&block.header.beneficiary, AccountState::default());
+    }
+    let bene_bal = state.get_balance(&block.header.beneficiary).unwrap_or(U256::zero());
+    state.set_balance(&block.header.beneficiary, bene_bal + reward);
+}
+
 impl Machine {
+    /// charge `cost` gas, running out of gas leaves gas_remaining untouched
+    pub fn use_gas(&mut self, cost: U256) -> Result<(), EvmError> {
+        if cost > self.gas_remaining {
+            return Err(EvmError::OutOfGas);
+        }
+        self.gas_remaining -= cost;
+        Ok(())
+    }
+
     pub fn stack_pop(&mut self) -> Result<U256, EvmError> {
         self.stack.pop().ok_or(EvmError::StackUnderflow)
     }
 
+    /// read the n-th item from the top of the stack (0 is the top) without popping it
+    pub fn stack_peek(&self, n: usize) -> Result<U256, EvmError> {
+        if n >= self.stack.len() {
+            return Err(EvmError::StackUnderflow);
+        }
+        Ok(self.stack[self.stack.len() - 1 - n])
+    }
+
     pub fn stack_push(&mut self, val: U256) -> Result<(), EvmError> {
         if self.stack.len() >= 1024 {
             return Err(EvmError::StackOverflow);
@@ -128,7 +231,16 @@ fn to_word_size(size: usize) -> usize {
     }
 }
 
-fn intrinsic_gas(tx: &Transaction1or2) -> u64 {
+const G_MEMORY: u64 = 3;
+const G_QUAD_COEFF_DIV: u64 = 512;
+
+// C_mem(a) = G_memory * a + floor(a^2 / 512), where a is the memory size in words
+fn memory_cost(words: usize) -> U256 {
+    let a = U256::from(words);
+    a * U256::from(G_MEMORY) + a * a / U256::from(G_QUAD_COEFF_DIV)
+}
+
+fn intrinsic_gas(tx: &Transaction1or2, spec: SpecId) -> u64 {
     let mut gas = 0;
 
     if tx.is_creation() {
@@ -142,8 +254,8 @@ fn intrinsic_gas(tx: &Transaction1or2) -> u64 {
     let non_zeros = tx.data.len() - zeros;
     gas += (non_zeros * 16 + zeros * 4) as u64;
 
-    // Extra cost for contract creation: 2 gas per "word" (32 bytes)
-    if tx.is_creation() {
+    // EIP-3860: 2 gas per "word" (32 bytes) of init code
+    if tx.is_creation() && spec.is_enabled_in(SpecId::Shanghai) {
         gas += 2 * to_word_size(tx.data.len()) as u64;
     }
 
@@ -160,7 +272,31 @@ fn intrinsic_gas(tx: &Transaction1or2) -> u64 {
     gas
 }
 
-fn create_address(
+// EIP-7623: a transaction pays at least 10 gas per calldata token, a zero byte is one token
+// and a non-zero byte four
+fn calldata_floor_gas(tx: &Transaction1or2, spec: SpecId) -> u64 {
+    if !spec.is_enabled_in(SpecId::Prague) {
+        return 0;
+    }
+    let zeros = tx.data.iter().filter(|&&b| b == 0).count();
+    let tokens = zeros + (tx.data.len() - zeros) * 4;
+    21000 + 10 * tokens as u64
+}
+
+// the gas left to return once the calldata floor has been charged
+fn apply_calldata_floor(tx: &Transaction1or2, spec: SpecId, gas_remaining: U256) -> U256 {
+    gas_remaining.min(U256::from(tx.gas_limit - calldata_floor_gas(tx, spec)))
+}
+
+// EIP-1559: there is no base fee to burn before London
+fn block_base_fee(block: &Block, spec: SpecId) -> U256 {
+    if !spec.is_enabled_in(SpecId::London) {
+        return U256::zero();
+    }
+    block.header.base_fee.unwrap_or(U256::zero())
+}
+
+pub(crate) fn create_address(
     sender: Address,
     nonce: u64,
     salt: Option<H256>,
@@ -187,7 +323,10 @@ fn create_address(
 }
 
 // correspond to python-evm validate_frontier_transaction
-fn check_valid_transaction(tx: &Transaction1or2, state: &WorldStateTrie, block: &Block) -> Result<(), anyhow::Error> {
+fn check_valid_transaction(tx: &Transaction1or2, state: &WorldStateTrie, block: &Block, spec: SpecId) -> Result<(), anyhow::Error> {
+    if tx.tx_type == 2 && !spec.is_enabled_in(SpecId::London) {
+        return Err(anyhow::anyhow!("dynamic fee transactions are not valid before London"));
+    }
     let sender = tx.get_sender()?;
 
     // nonce check and sender valid
@@ -206,13 +345,13 @@ fn check_valid_transaction(tx: &Transaction1or2, state: &WorldStateTrie, block:
     }
 
     // intrinsic gas
-    let intrinsic_gas = intrinsic_gas(tx);
-    if tx.gas_limit < intrinsic_gas {
+    let intrinsic_gas = intrinsic_gas(tx, spec);
+    if tx.gas_limit < intrinsic_gas.max(calldata_floor_gas(tx, spec)) {
         return Err(anyhow::anyhow!("gas limit too low"));
     }
 
     // sufficient account balance
-    let base_fee = block.header.base_fee.unwrap_or(U256::zero());
+    let base_fee = block_base_fee(block, spec);
     let upfront_cost = tx.upfront_cost(base_fee);
     if state.get_balance(&sender).unwrap() < upfront_cost {
         return Err(anyhow::anyhow!("insufficient balance"));
@@ -229,9 +368,9 @@ fn check_valid_transaction(tx: &Transaction1or2, state: &WorldStateTrie, block:
         return Err(anyhow::anyhow!("gas price ceiling {} is below base fee {}", m, base_fee));
     }
 
-    if tx.is_creation() {
+    if tx.is_creation() && spec.is_enabled_in(SpecId::Shanghai) {
         let data_len = tx.data.len();
-        if data_len > 49152 {
+        if data_len > MAX_INITCODE_SIZE {
             return Err(anyhow::anyhow!("data length too long"));
         }
     }
@@ -257,14 +396,56 @@ pub fn tx_execute(
     tx: &Transaction1or2,
     state: &mut WorldStateTrie,
     block: &mut Block,
+    ancestors: &[Block],
+    spec: SpecId,
+) -> Result<(), anyhow::Error> {
+    tx_execute_with_precompiles(tx, state, block, ancestors, spec, Precompiles::for_spec(spec))
+}
+
+/// like tx_execute, with the precompiled contracts taken from `precompiles` instead of the fork's
+pub fn tx_execute_with_precompiles(
+    tx: &Transaction1or2,
+    state: &mut WorldStateTrie,
+    block: &mut Block,
+    ancestors: &[Block],
+    spec: SpecId,
+    precompiles: &Precompiles,
+) -> Result<(), anyhow::Error> {
+    execute_transaction(tx, state, block, ancestors, spec, precompiles, None)
+}
+
+/// 同 `tx_execute_with_precompiles`，并返回这笔交易中 KECCAK256 的 哈希 => 原像，
+/// 可交给 `WorldStateTrie::debug_print_with_preimages` 还原 mapping 的 key。
+/// 回滚的帧与失败的交易不留下原像；需要跨交易保留时由调用方自行合并。
+pub fn tx_execute_recording_preimages(
+    tx: &Transaction1or2,
+    state: &mut WorldStateTrie,
+    block: &mut Block,
+    ancestors: &[Block],
+    spec: SpecId,
+    precompiles: &Precompiles,
+) -> Result<HashMap<H256, Vec<u8>>, anyhow::Error> {
+    let mut preimages = HashMap::new();
+    execute_transaction(tx, state, block, ancestors, spec, precompiles, Some(&mut preimages))?;
+    Ok(preimages)
+}
+
+fn execute_transaction(
+    tx: &Transaction1or2,
+    state: &mut WorldStateTrie,
+    block: &mut Block,
+    ancestors: &[Block],
+    spec: SpecId,
+    precompiles: &Precompiles,
+    preimages: Option<&mut HashMap<H256, Vec<u8>>>,
 ) -> Result<(), anyhow::Error> {
     // check transaction validity
-    check_valid_transaction(tx, state, block)?;
+    check_valid_transaction(tx, state, block, spec)?;
 
     // Preparation: Checkpoint State
     let sender = tx.get_sender()?;
-    let base_fee = block.header.base_fee.unwrap_or(U256::zero());
-    let g_0 = intrinsic_gas(tx);
+    let base_fee = block_base_fee(block, spec);
+    let g_0 = intrinsic_gas(tx, spec);
     let eff_price = tx.effective_gas_price(base_fee); // p
     
     // $$
@@ -327,7 +508,9 @@ pub fn tx_execute(
     // ── Step 2: build initial substate A* ────────────────────────────────────
     //   A*_a = {precompiles} ∪ {sender} ∪ {beneficiary} ∪ {to} ∪ {AL addrs}
     let mut warm_accounts = vec![sender];
-    warm_accounts.push(block.header.beneficiary);
+    if spec.is_enabled_in(SpecId::Shanghai) {
+        warm_accounts.push(block.header.beneficiary); // EIP-3651
+    }
     if let Some(to) = &tx.to {
         warm_accounts.push(*to);
     }
@@ -337,10 +520,7 @@ pub fn tx_execute(
     for item in &tx.access_list {
         warm_accounts.push(item.address);
     }
-    for item in &tx.access_list {
-        warm_accounts.push(item.address);
-    }
-    // TODO: push precompiles to warm_accounts
+    warm_accounts.extend(precompiles.addresses());
 
     // A*_K = all storage slots of access list }
     let mut warm_storage: Vec<(Address, H256)> = vec![];
@@ -352,59 +532,81 @@ pub fn tx_execute(
 
     let mut substate = Substate {
         self_destruct:        vec![],
+        created_accounts:     created_contract.into_iter().collect(),
         logs:                 vec![],
         touched_accounts:     vec![],
         refund_fee:           U256::zero(),
         access_list_accounts: warm_accounts,
         access_list_storage:  warm_storage,
+        preimages:            preimages.is_some().then(Vec::new),
     };
 
     let mut evm = Machine {
-        memory: Bytes::new(),
+        memory: vec![],
         stack: vec![],
         pc: 0,
         call_depth: 0,
         gas_remaining: U256::from(tx.gas_limit) - U256::from(g_0),
+        return_data: Bytes::new(),
     };
 
-    let code:Vec<u8>  = if let Some(to) = &tx.to {
-        state.get_code(to).unwrap_or_default()
+    let (code, code_hash) = if let Some(to) = &tx.to {
+        state.get_account(to)
+            .map(|account| (account.code, account.code_hash))
+            .unwrap_or_else(|| (vec![], H256::from_slice(&Keccak256::digest([]))))
     } else { // CREATE transaction
-        tx.data.to_vec()
+        (tx.data.to_vec(), H256::from_slice(&Keccak256::digest(&tx.data)))
     };
     
     let context = Context {
         contract_addr: tx.to.or(created_contract),
         origin_sender: sender,
         gas_price: tx.effective_gas_price(base_fee),
-        input: tx.data.clone(),
+        input: if tx.to.is_some() { tx.data.clone() } else { Bytes::new() }, // init code has no calldata
         sender,
         value: tx.value,
         code,
+        code_hash,
         block,
+        ancestors,
+        blob_hashes: vec![], // blob transactions are not supported yet
+        spec,
+        precompiles,
         depth: 0, // initial depth
-        allow_writes: true, // always allow writes for now
+        allow_writes: true, // only STATICCALL frames are read-only
     };
 
     if create_collision {
         evm.gas_remaining = U256::zero();
-        return settle_failed_execution(tx, state, block, evm.gas_remaining);
+        return settle_failed_execution(tx, state, block, spec, evm.gas_remaining);
     }
 
-    // run evm
-    let output_result = evm.run(&context, state, &mut substate);
+    // run evm, a transaction to a precompiled contract runs the precompile instead of the account code
+    let output_result = match tx.to.and_then(|to| precompiles.get(&to)) {
+        Some(precompile) => run_precompile(precompile, &mut evm, &context),
+        None => evm.run(&context, state, &mut substate),
+    };
+
+    // the transaction could not be executed at all, leave the state as it was
+    if let Err(EvmError::Fatal(reason)) = output_result {
+        let _ = state.rollback();
+        state.clear_transient_storage();
+        state.set_balance(&sender, sender_bal);
+        state.set_nonce(&sender, tx.nonce);
+        return Err(anyhow::anyhow!("transaction aborted: {}", reason));
+    }
 
     // Execution failed
     if output_result.is_err() {
         println!("Execution failed, result: {:?}", output_result);
-        return settle_failed_execution(tx, state, block, evm.gas_remaining);
+        return settle_failed_execution(tx, state, block, spec, evm.gas_remaining);
     }
 
     let output = output_result.unwrap();
     if let Some(contract_addr) = created_contract {
         let code_deposit_cost = U256::from(G_CODE_DEPOSIT) * U256::from(output.len());
         let contract_exists = state.account_exists(&contract_addr);
-        let starts_with_invalid_prefix = output.first() == Some(&0xef);
+        let starts_with_invalid_prefix = output.first() == Some(&0xef) && spec.is_enabled_in(SpecId::London);
         let create_exceptional = (create_collision)
             || (!contract_exists && !output.is_empty())
             || evm.gas_remaining < code_deposit_cost
@@ -412,7 +614,7 @@ pub fn tx_execute(
             || starts_with_invalid_prefix;
         if create_exceptional {
             evm.gas_remaining = U256::zero();
-            return settle_failed_execution(tx, state, block, evm.gas_remaining);
+            return settle_failed_execution(tx, state, block, spec, evm.gas_remaining);
         }
 
         if code_deposit_cost > U256::zero() {
@@ -425,12 +627,14 @@ pub fn tx_execute(
     // ── Step 6: refund  ──────────────────
     //   compute g*  (total gas to return to sender)
     //   g'  = evm.gas_remaining   (gas left after EVM execution)
-    //   g*  = g' + min( ⌊(T_g − g') / 5⌋,  A_r )    EIP-3529
+    //   g*  = g' + min( ⌊(T_g − g') / 5⌋,  A_r )    EIP-3529, ⌊(T_g − g') / 2⌋ before London
+    //   g* is then capped by the EIP-7623 calldata floor
     let g_prime           = evm.gas_remaining;
     let gas_consumed      = U256::from(tx.gas_limit) - g_prime;  // T_g − g'
-    let refund_cap        = gas_consumed / 5;                     // ⌊(T_g − g') / 5⌋
+    let refund_quotient   = if spec.is_enabled_in(SpecId::London) { 5 } else { 2 };
+    let refund_cap        = gas_consumed / refund_quotient;       // ⌊(T_g − g') / 5⌋
     let storage_refund    = substate.refund_fee.min(refund_cap);  // A_r, capped
-    let g_star            = g_prime + storage_refund;             // total gas returned
+    let g_star            = apply_calldata_floor(tx, spec, g_prime + storage_refund); // total gas returned
 
     // Step 7 : state finalization
     // \sigma^*[S(T)]_b \equiv \sigma_P[S(T)]_b + g^* \cdot p
@@ -442,13 +646,7 @@ pub fn tx_execute(
     // \sigma^*[B_{H_c}]_b \equiv \sigma_P[B_{H_c}]_b + (T_g - g^*) \cdot f
     let f                 = tx.priority_fee_per_gas(base_fee);
     let beneficiary_reward = (U256::from(tx.gas_limit) - g_star) * f;
-    if beneficiary_reward > U256::zero() {
-        if state.get_account(&block.header.beneficiary).is_none() {
-            state.insert(&block.header.beneficiary, AccountState::default());
-        }
-        let bene_bal = state.get_balance(&block.header.beneficiary).unwrap_or(U256::zero());
-        state.set_balance(&block.header.beneficiary, bene_bal + beneficiary_reward);
-    }
+    pay_beneficiary(state, block, beneficiary_reward);
     
     // step 8: finalize worldstate
     for addr in substate.self_destruct {
@@ -469,7 +667,12 @@ pub fn tx_execute(
     
     // Commit checkpoint on success
     state.commit();
-    
+    if let (Some(preimages), Some(recorded)) = (preimages, substate.preimages) {
+        preimages.extend(recorded);
+    }
+    // EIP-1153: transient storage does not outlive the transaction
+    state.clear_transient_storage();
+
     // receipt
     let gas_used_final = U256::from(tx.gas_limit) - g_star;
     block.header.gas_used += gas_used_final;
@@ -492,36 +695,15 @@ impl Machine {
     {
         loop {
             let opcode = self.get_opcode(context);
-            let operation = JUMP_TABLE.get(&opcode).ok_or(EvmError::InvalidOpcode)?;
-
-            // stack check
-            let stack_size = self.stack.len();
-            if stack_size < operation.min_stack {
-                return Err(EvmError::StackUnderflow);
-            }
-            if stack_size > operation.max_stack {
-                return Err(EvmError::StackOverflow);
-            }
-            // memory overflow check
-            if let Some(memory_size) = operation.memory_size {
-                let size = memory_size(self, context)?;
-                if size > self.memory.len() {
-                    // todo: expandable memory
-                    panic!("Not enough memory");
-                }
-            }
-            // gas
-            let cost = operation.constant_gas;
-            if U256::from(cost) > self.gas_remaining {
-                return Err(EvmError::OutOfGas);
-            }
-            self.gas_remaining -= U256::from(cost);
-            if let Some(dynamic_gas) = operation.dynamic_gas {
-                let dynamic_cost = dynamic_gas(self, context)?;
-                if dynamic_cost > self.gas_remaining {
-                    return Err(EvmError::OutOfGas);
-                }
-                self.gas_remaining -= U256::from(dynamic_cost);
+            let Some(operation) = jump_table(context.spec).get(&opcode) else {
+                self.gas_remaining = U256::zero();
+                return Err(EvmError::InvalidOpcode);
+            };
+
+            // exceptional halts before execution consume all the remaining gas
+            if let Err(e) = self.charge_operation(operation, context) {
+                self.gas_remaining = U256::zero();
+                return Err(e);
             }
             // write limit, jumpdest,return data length, is checked for specific operation
 
@@ -549,8 +731,45 @@ impl Machine {
                 }
             }
             
-            self.pc += 1;
+            // jumps leave pc one before the destination, like in geth
+            self.pc = self.pc.wrapping_add(1);
+        }
+    }
+
+    // stack bounds, constant gas, memory expansion and dynamic gas, in this order
+    fn charge_operation(&mut self, operation: &Operation, context: &Context) -> Result<(), EvmError> {
+        // stack check
+        let stack_size = self.stack.len();
+        if stack_size < operation.min_stack {
+            return Err(EvmError::StackUnderflow);
+        }
+        if stack_size > operation.max_stack {
+            return Err(EvmError::StackOverflow);
+        }
+        // gas
+        let cost = operation.constant_gas;
+        if U256::from(cost) > self.gas_remaining {
+            return Err(EvmError::OutOfGas);
+        }
+        self.gas_remaining -= U256::from(cost);
+        // memory expansion is paid before the memory is touched
+        if let Some(memory_size) = operation.memory_size {
+            let size = memory_size(self, context)?;
+            let expansion_cost = self.memory_expansion_cost(size);
+            if expansion_cost > self.gas_remaining {
+                return Err(EvmError::OutOfGas);
+            }
+            self.gas_remaining -= expansion_cost;
+            self.expand_memory(size);
+        }
+        if let Some(dynamic_gas) = operation.dynamic_gas {
+            let dynamic_cost = dynamic_gas(self, context)?;
+            if dynamic_cost > self.gas_remaining {
+                return Err(EvmError::OutOfGas);
+            }
+            self.gas_remaining -= dynamic_cost;
         }
+        Ok(())
     }
 
     fn get_opcode(&self, context: &Context) -> u8 {
@@ -561,13 +780,37 @@ impl Machine {
         context.code[self.pc]
     }
 
-    #[allow(dead_code)]
-    fn get_memory_slice(&self, offset: U256, size: U256) -> Result<Bytes, anyhow::Error> {
-        let offset = offset.as_usize();
-        let size = size.as_usize();
-        if offset + size > self.memory.len() {
-            return Err(anyhow::anyhow!("Memory out of bounds"));
+    /// C_mem(μ'_i) - C_mem(μ_i): the cost to grow the memory to cover `size` bytes
+    pub fn memory_expansion_cost(&self, size: usize) -> U256 {
+        let new_words = to_word_size(size);
+        let current_words = self.memory.len() / 32;
+        if new_words <= current_words {
+            return U256::zero();
+        }
+        memory_cost(new_words) - memory_cost(current_words)
+    }
+
+    /// grow the memory to cover `size` bytes, always to a multiple of 32 bytes
+    pub fn expand_memory(&mut self, size: usize) {
+        let new_len = to_word_size(size) * 32;
+        if new_len > self.memory.len() {
+            self.memory.resize(new_len, 0);
+        }
+    }
+
+    /// read memory[offset..offset + size], the range must already be expanded
+    pub fn memory_read(&self, offset: usize, size: usize) -> Bytes {
+        if size == 0 {
+            return Bytes::new();
+        }
+        Bytes::copy_from_slice(&self.memory[offset..offset + size])
+    }
+
+    /// write data to memory[offset..], the range must already be expanded
+    pub fn memory_write(&mut self, offset: usize, data: &[u8]) {
+        if data.is_empty() {
+            return;
         }
-        Ok(self.memory.slice(offset..offset + size))
+        self.memory[offset..offset + data.len()].copy_from_slice(data);
     }
-}
\ No newline at end of file
+}
@@ -4,8 +4,9 @@ use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use anyhow::Result;
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 
-use crate::common::mem_store;
+use crate::common::mem_store::{self, JumpdestBitmap};
 use crate::common::trie::{MyTrie, TrieCodec};
 
 pub type StorageTrie = MyTrie<U256, U256, StorageCodec>;
@@ -37,6 +38,11 @@ enum JournalEntry {
         address: Address,
         old_account: AccountState,
     },
+    TransientStorageChange {
+        address: Address,
+        key: U256,
+        old_value: U256,
+    },
 }
 
 /// state object. the σ(a)
@@ -155,6 +161,7 @@ pub struct WorldStateTrie {
     inner: MyTrie<Address, AccountState, StateCodec>,
     journal_stack: Vec<Vec<JournalEntry>>,
     backend: mem_store::MemBackend,
+    transient_storage: HashMap<(Address, U256), U256>, // EIP-1153，只在一笔交易内有效
 }
 
 impl fmt::Debug for WorldStateTrie {
@@ -176,6 +183,7 @@ impl WorldStateTrie {
             inner: MyTrie::new(),
             journal_stack: Vec::new(),
             backend: mem_store::MemBackend::new(),
+            transient_storage: HashMap::new(),
         }
     }
 
@@ -282,6 +290,9 @@ impl WorldStateTrie {
                 self.inner.insert(address, &old_account);
                 self.sync_account_to_backend(old_account);
             },
+            JournalEntry::TransientStorageChange { address, key, old_value } => {
+                self.set_transient_value(*address, *key, *old_value);
+            },
         }
     }
 
@@ -417,6 +428,36 @@ impl WorldStateTrie {
         self.get_storage(address, key)
     }
 
+    pub fn get_transient_storage(&self, address: &Address, key: U256) -> U256 {
+        self.transient_storage.get(&(*address, key)).copied().unwrap_or_default()
+    }
+
+    /// 写入 transient storage，与 storage 写入一样记录 journal，回滚的帧会撤销这些写入。
+    pub fn set_transient_storage(&mut self, address: &Address, key: U256, value: U256) {
+        let old_value = self.get_transient_storage(address, key);
+        if old_value != value {
+            self.push_journal(JournalEntry::TransientStorageChange {
+                address: *address,
+                key,
+                old_value,
+            });
+            self.set_transient_value(*address, key, value);
+        }
+    }
+
+    /// 清空 transient storage，在交易结束时调用。
+    pub fn clear_transient_storage(&mut self) {
+        self.transient_storage.clear();
+    }
+
+    fn set_transient_value(&mut self, address: Address, key: U256, value: U256) {
+        if value.is_zero() {
+            self.transient_storage.remove(&(address, key));
+        } else {
+            self.transient_storage.insert((address, key), value);
+        }
+    }
+
     pub fn root_hash(&self) -> H256 {
         self.inner.root_hash()
     }
@@ -428,6 +469,17 @@ impl WorldStateTrie {
         items.into_iter()
     }
 
+    /// 读取 `code_hash` 对应代码已缓存的 JUMPDEST 分析结果。
+    pub fn get_jumpdests(&self, code_hash: H256) -> Option<Arc<JumpdestBitmap>> {
+        self.backend.code.get_jumpdests(code_hash)
+    }
+
+    /// 缓存 `code_hash` 对应代码的 JUMPDEST 分析结果。
+    /// 同一哈希的代码不会改变，因此缓存无需失效，也不需要写入 journal。
+    pub fn put_jumpdests(&mut self, code_hash: H256, bitmap: Arc<JumpdestBitmap>) {
+        self.backend.code.put_jumpdests(code_hash, bitmap);
+    }
+
     pub fn account_exists(&self, address: &Address) -> bool {
         self.inner.get_ref(address).is_some()
     }
@@ -436,6 +488,12 @@ impl WorldStateTrie {
     /// 每个账户单独成块，包含 nonce、balance、storage_root、code_hash、code 及所有 storage 槽。
     /// 哈希与地址均以完整十六进制显示，不缩略。
     pub fn debug_print(&self) {
+        self.debug_print_with_preimages(&HashMap::new());
+    }
+
+    /// 同 `debug_print`，由 KECCAK256 得到的 storage 槽会附带显示其原像（如 mapping 的 key）。
+    /// `preimages` 来自 `tx_execute_recording_preimages` 的返回值，由调用方按交易收集。
+    pub fn debug_print_with_preimages(&self, preimages: &HashMap<H256, Vec<u8>>) {
         let mut count = 0u32;
         for (address, account) in self.iter() {
             count += 1;
@@ -451,7 +509,10 @@ impl WorldStateTrie {
             }
             println!("  storage: (total {} slots)", account.storage.iter().count());
             for (slot, value) in account.storage.iter() {
-                println!("    {} => {}", slot, value);
+                match preimages.get(&H256::from(slot.to_big_endian())) {
+                    Some(preimage) => println!("    {} => {} (keccak256 of 0x{})", slot, value, hex::encode(preimage)),
+                    None => println!("    {} => {}", slot, value),
+                }
             }
             println!();
         }
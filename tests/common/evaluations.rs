@@ -1,5 +1,7 @@
 use layer1::world_state::WorldStateTrie;
+use layer1::receipts::Log;
 use ethereum_types::U256;
+use sha3::{Digest, Keccak256};
 use anyhow::Result;
 
 pub fn compare_world_states(expected: &WorldStateTrie, actual: &WorldStateTrie) -> Result<()> {
@@ -55,4 +57,13 @@ pub fn compare_world_states(expected: &WorldStateTrie, actual: &WorldStateTrie)
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// the `logs` field of a state test post state is KEC(RLP(logs))
+pub fn compare_logs_hash(expected: &str, logs: &[Log]) -> Result<()> {
+    let actual = format!("0x{}", hex::encode(Keccak256::digest(rlp::encode_list(logs))));
+    if actual != expected.to_lowercase() {
+        anyhow::bail!("Logs hash mismatch: expected {}, got {} for logs {:?}", expected, actual, logs);
+    }
+    Ok(())
+}
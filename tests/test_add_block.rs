@@ -6,7 +6,8 @@ use std::fs;
 use sha3::{Digest, Keccak256};
 
 use layer1::block::Block;
-use layer1::blockchain::Blockchain;
+use layer1::blockchain::{Blockchain, BlockchainConfig};
+use layer1::spec::SpecId;
 
 // ============================================
 // 测试数据加载结构（与 JSON 格式匹配）
@@ -363,7 +364,10 @@ fn test_trans_type() {
 
     let genesis_rlp_hex = test.genesis_rlp.as_str();
     let genesis_block = decode_block_rlp(genesis_rlp_hex).expect("Failed to decode genesis block from RLP");
-    let mut blockchain = Blockchain::with_blocks_and_state(vec![genesis_block], initial_state);
+    let config = BlockchainConfig::new(test.network.parse::<SpecId>().unwrap())
+        .with_kzg_trusted_setup("tests/data/trusted_setup.txt");
+    let mut blockchain = Blockchain::with_blocks_and_state(vec![genesis_block], initial_state, config)
+        .expect("Failed to create blockchain");
 
     // start to test
     for block_json in &test.blocks {
@@ -1,19 +1,28 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use serde::Deserialize;
 use layer1::transaction::Transaction1or2;
-use layer1::tx_execution::tx_execute;
+use layer1::tx_execution::{tx_execute, tx_execute_recording_preimages};
+use layer1::operations::Precompiles;
+use ethereum_types::{H256, U256};
+use layer1::spec::SpecId;
+use layer1::common::crypto::load_kzg_trusted_setup;
 use serde_json::Value;
 use anyhow::Result;
 
 mod common;
-use common::parsers::{build_world_state_from_test, build_block_from_env, RawAccount, Env};
-use common::evaluations::compare_world_states;
+use common::parsers::{build_world_state_from_test, build_block_from_env, parse_address, parse_bytes, RawAccount, Env};
+use common::evaluations::{compare_world_states, compare_logs_hash};
 
 #[derive(Debug, Deserialize)]
 struct PostState {
     indexes: PostStateIndexes,
-    state: HashMap<String, RawAccount>,
+    // ethereum/tests 的旧格式只有 state root，没有完整的 post state
+    state: Option<HashMap<String, RawAccount>>,
+    logs: String,
     txbytes: String,
+    #[serde(rename = "expectException")]
+    expect_exception: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -29,28 +38,66 @@ fn test_tx_execution_against_post_state(raw_json: &Value, case_name: &str) -> Re
     let env: Env = serde_json::from_value(test["env"].clone())?;
     let pre: HashMap<String, RawAccount> = serde_json::from_value(test["pre"].clone())?;
 
-    // 3. 获取执行目标状态（以 Cancun 分叉为例）
-    let post_states: Vec<PostState> = serde_json::from_value(test["post"]["Cancun"].clone())?;
+    // 3. 按分叉获取执行目标状态，跳过执行器不支持或不认识的分叉
+    let forks = test["post"].as_object().ok_or_else(|| anyhow::anyhow!("no post states"))?;
+    for (fork, post_states) in forks {
+        let Ok(spec) = fork.parse::<SpecId>() else {
+            println!("Skipping unsupported fork: {}", fork);
+            continue;
+        };
+        let post_states: Vec<PostState> = serde_json::from_value(post_states.clone())?;
+        test_fork_post_states(&env, &pre, spec, &post_states)?;
+    }
+
+    Ok(())
+}
+
+fn test_fork_post_states(
+    env: &Env,
+    pre: &HashMap<String, RawAccount>,
+    spec: SpecId,
+    post_states: &[PostState],
+) -> Result<()> {
     for (i, post_state) in post_states.iter().enumerate() {
-        println!("Running post state index: {}", i);
+        println!("Running {} post state index: {}", spec, i);
 
         let _data_index = post_state.indexes.data;
+        let Some(post) = &post_state.state else {
+            return Err(anyhow::anyhow!("{} post state {} has only a state root, a full post state is needed", spec, i));
+        };
         let tx_hex = post_state.txbytes.strip_prefix("0x").unwrap_or(&post_state.txbytes);
         let tx_bytes = hex::decode(tx_hex)?;
-        let tx = Transaction1or2::deserialization(&tx_bytes)
-            .map_err(|e| anyhow::anyhow!("decode txbytes failed: {:?}", e))?;
+        let tx = match Transaction1or2::deserialization(&tx_bytes) {
+            Ok(tx) => tx,
+            // 无法解码的交易只能出现在预期异常的 case 中
+            Err(_) if post_state.expect_exception.is_some() => continue,
+            Err(e) => return Err(anyhow::anyhow!("decode txbytes failed: {:?}", e)),
+        };
 
-        let mut state = build_world_state_from_test(&pre);
-        let mut block = build_block_from_env(&env);
+        let mut state = build_world_state_from_test(pre);
+        let mut block = build_block_from_env(env);
 
-        // 5. 执行交易
-        tx_execute(&tx, &mut state, &mut block)?;
+        // 5. 执行交易，预期异常的交易必须被拒绝且不改变状态
+        let result = tx_execute(&tx, &mut state, &mut block, &[], spec);
+        match (&post_state.expect_exception, result) {
+            (Some(exception), Ok(())) => {
+                return Err(anyhow::anyhow!("{} post state {} expects {}, but the transaction was executed", spec, i, exception));
+            }
+            (None, Err(e)) => return Err(e),
+            _ => {}
+        }
 
         // 6. 构建预期状态
-        let expected_state = build_world_state_from_test(&post_state.state);
+        let expected_state = build_world_state_from_test(post);
 
         // 7. 比较状态树
         compare_world_states(&expected_state, &state)?;
+
+        // 8. 比较日志
+        if post_state.expect_exception.is_none() {
+            let receipt = block.receipts.last().ok_or_else(|| anyhow::anyhow!("no receipt"))?;
+            compare_logs_hash(&post_state.logs, &receipt.logs)?;
+        }
     }
 
     Ok(())
@@ -60,6 +107,9 @@ fn fixture_path(name: &str) -> String {
     format!("tests/data/{name}")
 }
 
+// the point evaluation precompile needs the mainnet KZG trusted setup
+const TRUSTED_SETUP_PATH: &str = "tests/data/trusted_setup.txt";
+
 #[test]
 fn test_add_json() -> Result<()> {
     let json_str = std::fs::read_to_string(fixture_path("add.json"))?;
@@ -75,4 +125,260 @@ fn test_create_contract_sstore_during_init_json() -> Result<()> {
         &json,
         "GeneralStateTests/stCreateTest/CREATE_ContractSSTOREDuringInit.json::CREATE_ContractSSTOREDuringInit-fork_[Cancun-Prague]-d0g0v0",
     )
-}
\ No newline at end of file
+}
+
+// 运行一个 fixture 文件中的全部 case，返回每个失败的 case 及其原因
+fn fixture_failures(path: &Path) -> Result<Vec<String>> {
+    load_kzg_trusted_setup(Path::new(TRUSTED_SETUP_PATH))?;
+    let json_str = std::fs::read_to_string(path)?;
+    let json: Value = serde_json::from_str(&json_str)?;
+    let cases = json.as_object().ok_or_else(|| anyhow::anyhow!("{} has no test cases", path.display()))?;
+
+    Ok(cases.keys()
+        .filter_map(|case_name| {
+            test_tx_execution_against_post_state(&json, case_name)
+                .err()
+                .map(|e| format!("{}: {:#}", case_name, e))
+        })
+        .collect())
+}
+
+fn run_fixture_file(file_name: &str) -> Result<()> {
+    let failures = fixture_failures(Path::new(&fixture_path(file_name)))?;
+    assert!(failures.is_empty(), "{} failing case(s) in {}:\n{}", failures.len(), file_name, failures.join("\n"));
+    Ok(())
+}
+
+// ethereum/tests 的官方 state test 套件：STATE_TESTS_DIR 指向 execution-spec-tests 发布的
+// fixtures/state_tests 目录（带完整 post state），套件按目录名查找，未设置时跳过
+const STATE_TESTS_DIR: &str = "STATE_TESTS_DIR";
+
+fn run_state_test_suite(suite: &str) -> Result<()> {
+    let Ok(root) = std::env::var(STATE_TESTS_DIR) else {
+        println!("{} is not set, skipping the {} state tests", STATE_TESTS_DIR, suite);
+        return Ok(());
+    };
+    let mut files = vec![];
+    collect_suite_files(Path::new(&root), suite, false, &mut files)?;
+    if files.is_empty() {
+        return Err(anyhow::anyhow!("no {} state tests under {}", suite, root));
+    }
+    files.sort();
+
+    let mut failures = vec![];
+    for file in &files {
+        failures.extend(fixture_failures(file)?.into_iter().map(|failure| format!("{}: {}", file.display(), failure)));
+    }
+    assert!(failures.is_empty(), "{} failing case(s) in {}:\n{}", failures.len(), suite, failures.join("\n"));
+    Ok(())
+}
+
+// 收集名为 suite 的目录下的全部 json 文件
+fn collect_suite_files(dir: &Path, suite: &str, in_suite: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            let in_suite = in_suite || path.file_name().is_some_and(|name| name == suite);
+            collect_suite_files(&path, suite, in_suite, files)?;
+        } else if in_suite && path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_arithmetic_json() -> Result<()> {
+    run_fixture_file("arithmetic.json")
+}
+
+#[test]
+fn test_comparison_bitwise_json() -> Result<()> {
+    run_fixture_file("comparison_bitwise.json")
+}
+
+#[test]
+fn test_memory_json() -> Result<()> {
+    run_fixture_file("memory.json")
+}
+
+#[test]
+fn test_control_flow_json() -> Result<()> {
+    run_fixture_file("control_flow.json")
+}
+
+#[test]
+fn test_stack_json() -> Result<()> {
+    run_fixture_file("stack.json")
+}
+
+#[test]
+fn test_environment_json() -> Result<()> {
+    run_fixture_file("environment.json")
+}
+
+#[test]
+fn test_block_context_json() -> Result<()> {
+    run_fixture_file("block_context.json")
+}
+
+#[test]
+fn test_sload_json() -> Result<()> {
+    run_fixture_file("sload.json")
+}
+
+#[test]
+fn test_log_json() -> Result<()> {
+    run_fixture_file("log.json")
+}
+
+#[test]
+fn test_return_json() -> Result<()> {
+    run_fixture_file("return.json")
+}
+
+#[test]
+fn test_call_json() -> Result<()> {
+    run_fixture_file("call.json")
+}
+
+#[test]
+fn test_call_variants_json() -> Result<()> {
+    run_fixture_file("call_variants.json")
+}
+
+#[test]
+fn test_create_json() -> Result<()> {
+    run_fixture_file("create.json")
+}
+
+#[test]
+fn test_selfdestruct_json() -> Result<()> {
+    run_fixture_file("selfdestruct.json")
+}
+
+#[test]
+fn test_transient_storage_json() -> Result<()> {
+    run_fixture_file("transient_storage.json")
+}
+
+#[test]
+fn test_keccak_json() -> Result<()> {
+    run_fixture_file("keccak.json")
+}
+
+#[test]
+fn test_keccak_preimages_are_returned_per_transaction() -> Result<()> {
+    let json_str = std::fs::read_to_string(fixture_path("keccak.json"))?;
+    let json: Value = serde_json::from_str(&json_str)?;
+    let test = &json["keccak256_mapping_slot"];
+    let env: Env = serde_json::from_value(test["env"].clone())?;
+    let pre: HashMap<String, RawAccount> = serde_json::from_value(test["pre"].clone())?;
+    let post_state: PostState = serde_json::from_value(test["post"]["Cancun"][0].clone())?;
+    let tx = Transaction1or2::deserialization(&parse_bytes(&post_state.txbytes))
+        .map_err(|e| anyhow::anyhow!("decode txbytes failed: {:?}", e))?;
+
+    let mut state = build_world_state_from_test(&pre);
+    let mut block = build_block_from_env(&env);
+    let spec = SpecId::Cancun;
+    let preimages = tx_execute_recording_preimages(&tx, &mut state, &mut block, &[], spec, Precompiles::for_spec(spec))?;
+
+    // 合约把 keccak256(0x2a . 3) 写入 slot 0
+    let mut mapping_key = [0u8; 64];
+    mapping_key[31] = 0x2a;
+    mapping_key[63] = 3;
+    let slot_0 = state.get_storage(&parse_address("0x0000000000000000000000000000000000001000"), U256::zero()).unwrap();
+    assert_eq!(preimages.len(), 2);
+    assert_eq!(preimages.get(&H256::from(slot_0.to_big_endian())), Some(&mapping_key.to_vec()));
+    Ok(())
+}
+
+#[test]
+fn test_ecrecover_json() -> Result<()> {
+    run_fixture_file("ecrecover.json")
+}
+
+#[test]
+fn test_hash_precompiles_json() -> Result<()> {
+    run_fixture_file("hash_precompiles.json")
+}
+
+#[test]
+fn test_modexp_json() -> Result<()> {
+    run_fixture_file("modexp.json")
+}
+
+#[test]
+fn test_bn254_json() -> Result<()> {
+    run_fixture_file("bn254.json")
+}
+
+#[test]
+fn test_blake2f_json() -> Result<()> {
+    run_fixture_file("blake2f.json")
+}
+
+#[test]
+fn test_point_evaluation_json() -> Result<()> {
+    run_fixture_file("point_evaluation.json")
+}
+
+#[test]
+fn test_forks_json() -> Result<()> {
+    run_fixture_file("forks.json")
+}
+
+#[test]
+fn test_precompile_tx_json() -> Result<()> {
+    run_fixture_file("precompile_tx.json")
+}
+
+#[test]
+fn test_state_tests_call_codes() -> Result<()> {
+    run_state_test_suite("stCallCodes")
+}
+
+#[test]
+fn test_state_tests_call_create_call_code_test() -> Result<()> {
+    run_state_test_suite("stCallCreateCallCodeTest")
+}
+
+#[test]
+fn test_state_tests_static_call() -> Result<()> {
+    run_state_test_suite("stStaticCall")
+}
+
+#[test]
+fn test_state_tests_delegatecall_test_homestead() -> Result<()> {
+    run_state_test_suite("stDelegatecallTestHomestead")
+}
+
+#[test]
+fn test_state_tests_return_data_test() -> Result<()> {
+    run_state_test_suite("stReturnDataTest")
+}
+
+#[test]
+fn test_state_tests_create2() -> Result<()> {
+    run_state_test_suite("stCreate2")
+}
+
+#[test]
+fn test_state_tests_eip1559() -> Result<()> {
+    run_state_test_suite("stEIP1559")
+}
+
+#[test]
+fn test_state_tests_eip3651_warm_coinbase() -> Result<()> {
+    run_state_test_suite("stEIP3651-warmcoinbase")
+}
+
+#[test]
+fn test_state_tests_eip3855_push0() -> Result<()> {
+    run_state_test_suite("stEIP3855-push0")
+}
+
+#[test]
+fn test_state_tests_eip3860_limit_meter_initcode() -> Result<()> {
+    run_state_test_suite("stEIP3860-limitmeterinitcode")
+}